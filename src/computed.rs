@@ -0,0 +1,83 @@
+use meval::{Context, ContextProvider, Expr};
+use serde::{Deserialize, Serialize};
+
+/// A column calculated from the parsed columns of each sample, e.g. `col0 - col1`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComputedColumn {
+    pub name: String,
+    pub expression: String,
+    /// the compiled `expression`, `None` if it is invalid
+    #[serde(skip)]
+    pub expr: Option<Expr>,
+}
+
+impl Default for ComputedColumn {
+    fn default() -> Self {
+        Self {
+            name: "Computed".to_string(),
+            expression: "col0".to_string(),
+            expr: None,
+        }
+    }
+}
+
+// the compiled expression follows from the text
+impl PartialEq for ComputedColumn {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.expression == other.expression
+    }
+}
+
+impl ComputedColumn {
+    pub fn compile(&mut self) -> Result<(), meval::Error> {
+        self.expr = None;
+        self.expr = Some(self.expression.parse()?);
+        Ok(())
+    }
+
+    /// The name in the dataset, marked so that it is not mistaken for a received column
+    /// (e.g. in a saved CSV file).
+    pub fn column_name(&self) -> String {
+        format!("{} (computed)", self.name)
+    }
+}
+
+/// The values of one sample, available as `col0`, `col1`, .. or by the column names.
+struct Columns<'a> {
+    values: &'a [f64],
+    names: &'a [String],
+}
+
+impl ContextProvider for Columns<'_> {
+    fn get_var(&self, name: &str) -> Option<f64> {
+        let index = match name
+            .strip_prefix("col")
+            .and_then(|i| i.parse::<usize>().ok())
+        {
+            Some(index) => index,
+            None => self.names.iter().position(|n| n == name)?,
+        };
+        self.values.get(index).copied()
+    }
+}
+
+thread_local! {
+    /// the built-in functions and constants (`sin`, `sqrt`, `pi`, ..)
+    static BUILTIN: Context<'static> = Context::new();
+}
+
+/// Evaluates the computed columns for one sample of parsed `values`. A column which can not be
+/// evaluated (invalid expression, unknown column) is NaN, so that it leaves a gap in the plot.
+pub fn evaluate(columns: &[ComputedColumn], values: &[f64], names: &[String]) -> Vec<f64> {
+    BUILTIN.with(|builtin| {
+        let context = (Columns { values, names }, builtin);
+        columns
+            .iter()
+            .map(|column| match &column.expr {
+                Some(expr) => expr.eval_with_context(&context).unwrap_or(f64::NAN),
+                None => f64::NAN,
+            })
+            .collect()
+    })
+}
@@ -1,6 +1,9 @@
+use std::collections::VecDeque;
 use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use chrono::{DateTime, Local};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum SerialDirection {
     Send,
@@ -23,12 +26,32 @@ pub fn get_epoch_ms() -> u128 {
         .as_millis()
 }
 
+/// Formats an epoch timestamp in ms as local wall-clock time `HH:MM:SS.mmm`.
+pub fn format_time_of_day(epoch_ms: u128) -> String {
+    match DateTime::from_timestamp_millis(epoch_ms as i64) {
+        Some(t) => t.with_timezone(&Local).format("%H:%M:%S%.3f").to_string(),
+        None => "--:--:--.---".to_string(),
+    }
+}
+
+/// Formats an epoch timestamp in ms as local wall-clock time `HH:MM:SS`.
+pub fn format_clock_time(epoch_ms: u128) -> String {
+    match DateTime::from_timestamp_millis(epoch_ms as i64) {
+        Some(t) => t.with_timezone(&Local).format("%H:%M:%S").to_string(),
+        None => "--:--:--".to_string(),
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Packet {
     pub relative_time: u128,
     pub absolute_time: u128,
     pub direction: SerialDirection,
     pub payload: String,
+    /// the bytes as they were received (with the terminator) or sent, for the raw recording
+    pub raw: Vec<u8>,
+    /// values decoded from a binary frame, the payload then shows the frame bytes in hex
+    pub values: Option<Vec<f64>>,
 }
 
 impl Default for Packet {
@@ -38,27 +61,175 @@ impl Default for Packet {
             absolute_time: get_epoch_ms(),
             direction: SerialDirection::Send,
             payload: "".to_string(),
+            raw: vec![],
+            values: None,
         }
     }
 }
 
+/// A labeled point in time shown on the plot, e.g. a command sent to the device.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    /// on the time axis of the samples, like `DataContainer::time`
+    pub relative_time: u128,
+    pub absolute_time: u128,
+    pub label: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct DataContainer {
-    pub time: Vec<u128>,
+    pub time: VecDeque<u128>,
     pub names: Vec<String>,
-    pub absolute_time: Vec<u128>,
-    pub dataset: Vec<Vec<f64>>,
+    pub absolute_time: VecDeque<u128>,
+    pub dataset: Vec<VecDeque<f64>>,
     pub raw_traffic: Vec<Packet>,
+    /// number of packets added to `raw_traffic`, including the ones trimmed from it
+    pub total_raw_packets: usize,
+    /// number of computed columns at the end of `dataset`
+    pub computed_columns: usize,
+    /// the series whose newest sample is outside of its alarm limits
+    pub alarms_active: Vec<bool>,
+    /// total payload size of `raw_traffic`
+    pub raw_traffic_bytes: usize,
+    /// column names read from a header line (if the header mode is enabled)
+    pub header: Option<Vec<String>>,
+    /// number of samples appended so far, including the ones trimmed from the buffer
+    pub total_samples: usize,
+    /// consecutive lines with a different number of values than the dataset
+    pub mismatched_lines: usize,
+    /// lines dropped because of a different number of values
+    pub dropped_lines: usize,
+    /// share of the latest received lines which were parsed into a sample, `None` before the
+    /// first line
+    pub parse_quality: Option<f64>,
+    /// samples appended during the last second
+    pub samples_per_second: f64,
+    /// packets waiting to be parsed
+    pub backlog: usize,
+    /// the backlog keeps growing, the data arrives faster than it is parsed
+    pub lagging: bool,
+    /// no data arrived within the watchdog timeout
+    pub stalled: bool,
+    /// bytes of the received lines (without line endings)
+    pub received_bytes: usize,
+    pub received_lines: usize,
+    /// bytes received during the last second
+    pub bytes_per_second: f64,
+    /// markers within the time span of the buffer
+    pub markers: VecDeque<Marker>,
+    /// number of the sample (counted like `total_samples`) which fired the trigger
+    pub trigger_sample: Option<usize>,
+    /// the samples after the trigger are complete, the buffer is frozen until re-armed
+    pub trigger_captured: bool,
 }
 
 impl Default for DataContainer {
     fn default() -> DataContainer {
         DataContainer {
-            time: vec![],
+            time: VecDeque::new(),
             names: vec!["Column 0".to_string()],
-            absolute_time: vec![],
-            dataset: vec![vec![]],
+            absolute_time: VecDeque::new(),
+            dataset: vec![VecDeque::new()],
             raw_traffic: vec![],
+            total_raw_packets: 0,
+            computed_columns: 0,
+            alarms_active: vec![],
+            raw_traffic_bytes: 0,
+            header: None,
+            total_samples: 0,
+            mismatched_lines: 0,
+            dropped_lines: 0,
+            parse_quality: None,
+            samples_per_second: 0.0,
+            backlog: 0,
+            lagging: false,
+            stalled: false,
+            received_bytes: 0,
+            received_lines: 0,
+            bytes_per_second: 0.0,
+            markers: VecDeque::new(),
+            trigger_sample: None,
+            trigger_captured: false,
+        }
+    }
+}
+
+impl DataContainer {
+    /// Position of the trigger sample in the buffer, if it is still in there.
+    pub fn trigger_index(&self) -> Option<usize> {
+        let first_sample = self.total_samples.checked_sub(self.time.len())?;
+        self.trigger_sample?
+            .checked_sub(first_sample)
+            .filter(|idx| *idx < self.time.len())
+    }
+
+    /// A copy of the samples (and the raw traffic) whose time is within `start..=end` (in ms).
+    pub fn slice_time(&self, start: u128, end: u128) -> DataContainer {
+        let first = self.time.partition_point(|t| *t < start);
+        let last = self.time.partition_point(|t| *t <= end).max(first);
+        DataContainer {
+            time: self.time.range(first..last).copied().collect(),
+            absolute_time: self
+                .absolute_time
+                .iter()
+                .skip(first)
+                .take(last - first)
+                .copied()
+                .collect(),
+            dataset: self
+                .dataset
+                .iter()
+                .map(|set| set.iter().skip(first).take(last - first).copied().collect())
+                .collect(),
+            names: self.names.clone(),
+            raw_traffic: self
+                .raw_traffic
+                .iter()
+                .filter(|p| (start..=end).contains(&p.relative_time))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Summary of the values of one series.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeriesStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl SeriesStats {
+    /// Returns `None` for an empty series, non-finite values are ignored.
+    pub fn from_values(values: &VecDeque<f64>) -> Option<SeriesStats> {
+        let mut count = 0;
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        let mut sum = 0.0;
+        for &value in values.iter().filter(|v| v.is_finite()) {
+            count += 1;
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+        }
+        if count == 0 {
+            return None;
         }
+        let mean = sum / count as f64;
+        let variance = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .map(|v| (v - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        Some(SeriesStats {
+            min,
+            max,
+            mean,
+            std_dev: variance.sqrt(),
+        })
     }
 }
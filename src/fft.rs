@@ -0,0 +1,66 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use realfft::RealFftPlanner;
+
+/// Estimates the sample rate in Hz from the median time step (in ms) of the samples.
+pub fn estimate_sample_rate(time: &VecDeque<u128>) -> Option<f64> {
+    let mut deltas: Vec<u128> = time
+        .iter()
+        .zip(time.iter().skip(1))
+        .map(|(t0, t1)| t1.saturating_sub(*t0))
+        .collect();
+    if deltas.is_empty() {
+        return None;
+    }
+    deltas.sort_unstable();
+    let median = deltas[deltas.len() / 2];
+    if median == 0 {
+        return None;
+    }
+    Some(1000.0 / median as f64)
+}
+
+/// Magnitude spectrum `[frequency, magnitude]` of the last `fft_size` values, weighted with a
+/// Hann window. Shorter series are zero-padded. The planner caches the plan of each size.
+pub fn magnitude_spectrum(
+    planner: &mut RealFftPlanner<f64>,
+    values: &VecDeque<f64>,
+    fft_size: usize,
+    sample_rate: f64,
+) -> Vec<[f64; 2]> {
+    let samples = values.len().min(fft_size);
+    if samples == 0 || fft_size < 2 {
+        return vec![];
+    }
+    let window: Vec<f64> = (0..samples)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / samples as f64).cos())
+        .collect();
+    let window_sum: f64 = window.iter().sum();
+
+    let r2c = planner.plan_fft_forward(fft_size);
+    let mut input = r2c.make_input_vec();
+    for ((x, value), w) in input
+        .iter_mut()
+        .zip(values.iter().skip(values.len() - samples))
+        .zip(window.iter())
+    {
+        // non-finite values would spoil the whole spectrum
+        *x = if value.is_finite() { value * w } else { 0.0 };
+    }
+    let mut spectrum = r2c.make_output_vec();
+    if r2c.process(&mut input, &mut spectrum).is_err() {
+        return vec![];
+    }
+
+    let scale = if window_sum > 0.0 {
+        2.0 / window_sum
+    } else {
+        0.0
+    };
+    spectrum
+        .iter()
+        .enumerate()
+        .map(|(k, c)| [k as f64 * sample_rate / fft_size as f64, c.norm() * scale])
+        .collect()
+}
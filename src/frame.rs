@@ -0,0 +1,164 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The type of one field of a binary frame.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FieldType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    F64,
+}
+
+impl FieldType {
+    pub const ALL: [FieldType; 8] = [
+        FieldType::U8,
+        FieldType::I8,
+        FieldType::U16,
+        FieldType::I16,
+        FieldType::U32,
+        FieldType::I32,
+        FieldType::F32,
+        FieldType::F64,
+    ];
+
+    /// Size in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::U8 | FieldType::I8 => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+            FieldType::U32 | FieldType::I32 | FieldType::F32 => 4,
+            FieldType::F64 => 8,
+        }
+    }
+
+    /// Decodes the value from exactly `self.size()` bytes.
+    fn decode(&self, bytes: &[u8], little_endian: bool) -> f64 {
+        macro_rules! decode {
+            ($t:ty) => {{
+                let bytes = bytes.try_into().unwrap_or_default();
+                if little_endian {
+                    <$t>::from_le_bytes(bytes) as f64
+                } else {
+                    <$t>::from_be_bytes(bytes) as f64
+                }
+            }};
+        }
+        match self {
+            FieldType::U8 => decode!(u8),
+            FieldType::I8 => decode!(i8),
+            FieldType::U16 => decode!(u16),
+            FieldType::I16 => decode!(i16),
+            FieldType::U32 => decode!(u32),
+            FieldType::I32 => decode!(i32),
+            FieldType::F32 => decode!(f32),
+            FieldType::F64 => decode!(f64),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FieldType::U8 => write!(f, "u8"),
+            FieldType::I8 => write!(f, "i8"),
+            FieldType::U16 => write!(f, "u16"),
+            FieldType::I16 => write!(f, "i16"),
+            FieldType::U32 => write!(f, "u32"),
+            FieldType::I32 => write!(f, "i32"),
+            FieldType::F32 => write!(f, "f32"),
+            FieldType::F64 => write!(f, "f64"),
+        }
+    }
+}
+
+/// Describes a fixed-size binary frame: the sync bytes followed by the fields.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FrameLayout {
+    /// decode binary frames instead of reading text lines
+    pub enable: bool,
+    pub sync: Vec<u8>,
+    pub fields: Vec<FieldType>,
+    pub little_endian: bool,
+}
+
+impl Default for FrameLayout {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            sync: vec![0xAA],
+            fields: vec![FieldType::F32],
+            little_endian: true,
+        }
+    }
+}
+
+impl FrameLayout {
+    /// Size of a whole frame in bytes, including the sync bytes.
+    pub fn frame_len(&self) -> usize {
+        self.sync.len() + self.fields.iter().map(|f| f.size()).sum::<usize>()
+    }
+}
+
+/// Reassembles frames from bytes which may be split over several reads.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    /// Appends the bytes and returns the complete frames, as raw bytes and decoded values.
+    /// Bytes in front of a sync sequence are skipped, so the decoder resyncs after garbage.
+    pub fn push(&mut self, bytes: &[u8], layout: &FrameLayout) -> Vec<(Vec<u8>, Vec<f64>)> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = vec![];
+        if layout.fields.is_empty() {
+            self.buffer.clear();
+            return frames;
+        }
+        let frame_len = layout.frame_len();
+        loop {
+            let start = if layout.sync.is_empty() {
+                Some(0)
+            } else {
+                self.buffer
+                    .windows(layout.sync.len())
+                    .position(|window| window == layout.sync.as_slice())
+            };
+            match start {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    // the end might be the beginning of the next sync sequence
+                    let keep = layout.sync.len() - 1;
+                    self.buffer.drain(..self.buffer.len().saturating_sub(keep));
+                    break;
+                }
+            }
+            if self.buffer.len() < frame_len {
+                break;
+            }
+            let frame: Vec<u8> = self.buffer.drain(..frame_len).collect();
+            let mut offset = layout.sync.len();
+            let values = layout
+                .fields
+                .iter()
+                .map(|field| {
+                    let value =
+                        field.decode(&frame[offset..offset + field.size()], layout.little_endian);
+                    offset += field.size();
+                    value
+                })
+                .collect();
+            frames.push((frame, values));
+        }
+        frames
+    }
+}
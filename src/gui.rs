@@ -1,26 +1,47 @@
 use core::f32;
+use std::collections::HashMap;
+use std::fmt;
 use std::path::PathBuf;
-use std::sync::mpsc::Sender;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use eframe::egui::{Align2, FontFamily, FontId, KeyboardShortcut, Pos2, Sense, SidePanel, Vec2};
+use chrono::Local;
+use crossbeam_channel::Sender;
+use eframe::egui::{
+    Align2, FontFamily, FontId, KeyboardShortcut, Pos2, Sense, SidePanel, Vec2, Visuals,
+};
 use eframe::{egui, Storage};
 use preferences::Preferences;
+use realfft::RealFftPlanner;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::data::{DataContainer, SerialDirection};
+use crate::data::{format_time_of_day, DataContainer, SerialDirection, SeriesStats};
+use crate::io::{
+    save_image, AutoSaveOptions, FileFormat, FileOptions, ImageOptions, OverwritePolicy,
+};
+use crate::parser::ParsingOptions;
 use crate::record::RecordOptions;
-use crate::serial::{save_serial_settings, Device, SerialDevices};
-use crate::GuiEvent;
-use crate::{APP_INFO, PREFS_KEY};
+use crate::serial::{
+    save_serial_settings, ByteLog, ConnectionStatus, Device, PortControl, PortInfo, SendPayload,
+    SerialDevices, SourceType,
+};
+use crate::{spawn_connection, Connection, ConnectionThreads, GuiEvent};
+use crate::{APP_INFO, PREFS_KEY, PREFS_KEY_PROFILES};
 
 mod components;
 
 const MAX_FPS: f64 = 60.0;
+const STATS_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+const Y_RANGE_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
 
 const DEFAULT_FONT_ID: FontId = FontId::new(14.0, FontFamily::Monospace);
 pub const RIGHT_PANEL_WIDTH: f32 = 350.0;
+/// the side panel can be resized within these limits, the plot keeps at least
+/// the minimum window width minus `MAX_PANEL_WIDTH`
+const MIN_PANEL_WIDTH: f32 = 250.0;
+const MAX_PANEL_WIDTH: f32 = 1.5 * RIGHT_PANEL_WIDTH;
 const BAUD_RATES: &[u32] = &[
     300, 1200, 2400, 4800, 9600, 19200, 38400, 57600, 74880, 115200, 230400, 128000, 460800,
     576000, 921600,
@@ -84,11 +105,7 @@ impl Print {
                 })
             }
             Print::Debug(s) => {
-                let color = if gui_conf.dark_mode {
-                    egui::Color32::YELLOW
-                } else {
-                    egui::Color32::LIGHT_RED
-                };
+                let color = egui::Color32::GRAY;
                 Some(ScrollAreaMessage {
                     label: "[DBG] ".to_owned(),
                     content: s.to_owned(),
@@ -96,7 +113,11 @@ impl Print {
                 })
             }
             Print::Ok(s) => {
-                let color = egui::Color32::GREEN;
+                let color = if gui_conf.dark_mode {
+                    egui::Color32::GREEN
+                } else {
+                    egui::Color32::DARK_GREEN
+                };
                 Some(ScrollAreaMessage {
                     label: "[OK] ".to_owned(),
                     content: s.to_owned(),
@@ -107,17 +128,53 @@ impl Print {
     }
 }
 
-#[allow(dead_code)]
 pub struct ScrollAreaMessage {
     label: String,
     content: String,
     color: egui::Color32,
 }
 
+/// Formats a number of bytes with a binary prefix, e.g. `1.5 KiB`.
+fn format_bytes(bytes: f64) -> String {
+    let mut value = bytes;
+    for unit in ["B", "KiB", "MiB"] {
+        if value < 1024.0 {
+            return if unit == "B" {
+                format!("{value:.0} {unit}")
+            } else {
+                format!("{value:.1} {unit}")
+            };
+        }
+        value /= 1024.0;
+    }
+    format!("{value:.1} GiB")
+}
+
+/// number of console lines that are kept, set from the GUI settings
+static CONSOLE_SCROLLBACK: AtomicUsize = AtomicUsize::new(DEFAULT_CONSOLE_SCROLLBACK);
+const DEFAULT_CONSOLE_SCROLLBACK: usize = 5000;
+
+/// Changes the scrollback of the console and drops the lines above it.
+fn set_console_scrollback(print_lock: &Arc<RwLock<Vec<Print>>>, lines: usize) {
+    CONSOLE_SCROLLBACK.store(lines.max(1), Ordering::Relaxed);
+    if let Ok(mut write_guard) = print_lock.write() {
+        trim_console(&mut write_guard);
+    }
+}
+
+/// Drops the oldest lines of the console above the scrollback.
+fn trim_console(prints: &mut Vec<Print>) {
+    let excess = prints
+        .len()
+        .saturating_sub(CONSOLE_SCROLLBACK.load(Ordering::Relaxed));
+    prints.drain(..excess);
+}
+
 pub fn print_to_console(print_lock: &Arc<RwLock<Vec<Print>>>, message: Print) {
     match print_lock.write() {
         Ok(mut write_guard) => {
             write_guard.push(message);
+            trim_console(&mut write_guard);
         }
         Err(e) => {
             println!("Error while writing to print_lock: {}", e);
@@ -132,19 +189,97 @@ pub struct Command {
     editing: bool,
 }
 
+/// Lines which are sent one after the other, e.g. to set up a device.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct CommandSequence {
+    /// one command per line
+    lines: String,
+    delay_ms: u64,
+    repeat: usize,
+}
+
+impl Default for CommandSequence {
+    fn default() -> Self {
+        Self {
+            lines: "".to_string(),
+            delay_ms: 100,
+            repeat: 1,
+        }
+    }
+}
+
+/// How files are sent to the device.
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct SendFileOptions {
+    chunk_size: usize,
+    /// pause after each chunk, to let the device keep up
+    delay_ms: u64,
+}
+
+impl Default for SendFileOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64,
+            delay_ms: 10,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
 pub struct GuiSettingsContainer {
     pub device: String,
     pub baud: u32,
     pub debug: bool,
     pub x: f32,
     pub y: f32,
+    pub active_tab: Option<GuiTabs>,
+    pub right_panel_expanded: bool,
+    pub right_panel_width: f32,
+    pub plot_serial_display_ratio: f32,
+    pub view_layout: ViewLayout,
+    /// width of the raw traffic in the side by side layout
+    pub raw_traffic_width: f32,
     pub save_absolute_time: bool,
+    pub append_csv: bool,
+    pub save_format: FileFormat,
+    /// decimal places of the values in a saved CSV file, full precision if `None`
+    pub save_precision: Option<usize>,
+    /// write the port, capture start and sample count as comment lines before the CSV header
+    pub save_metadata: bool,
+    /// write a leading column with the sample numbers into a saved CSV file
+    pub save_index: bool,
+    /// what happens if the chosen file exists
+    pub overwrite_policy: OverwritePolicy,
+    pub auto_save_options: AutoSaveOptions,
+    pub theme: ThemePreference,
+    /// whether the applied theme is dark, follows `theme`
     pub dark_mode: bool,
+    /// scales the whole UI, also changed with the zoom shortcuts (ctrl +/-/0)
+    pub zoom_factor: f32,
     pub plot_options: PlotOptions,
+    pub parsing_options: ParsingOptions,
     pub raw_traffic_options: RawTrafficOptions,
     pub record_options: RecordOptions,
+    pub fft_options: FftOptions,
+    pub readout_options: ReadoutOptions,
+    pub alarm_options: AlarmOptions,
     pub commands: Vec<Command>,
+    pub sequence: CommandSequence,
+    pub send_file: SendFileOptions,
+    pub tcp_address: String,
+    pub break_duration_ms: u64,
+    /// clear the data when connecting instead of appending to it
+    pub clear_on_connect: bool,
+    /// raise an error when no data arrives for `watchdog_seconds`
+    pub watchdog: bool,
+    pub watchdog_seconds: f64,
+    pub history: Vec<String>,
+    pub history_size: usize,
+    /// lines kept in the debug console, the oldest ones are dropped first
+    pub console_scrollback: usize,
 }
 
 impl Default for GuiSettingsContainer {
@@ -155,16 +290,45 @@ impl Default for GuiSettingsContainer {
             debug: true,
             x: 1600.0,
             y: 900.0,
+            active_tab: Some(GuiTabs::PlotOptions),
+            right_panel_expanded: true,
+            right_panel_width: RIGHT_PANEL_WIDTH,
+            plot_serial_display_ratio: 0.75,
+            view_layout: ViewLayout::default(),
+            raw_traffic_width: 450.0,
             save_absolute_time: false,
+            append_csv: false,
+            save_format: FileFormat::Csv,
+            save_precision: None,
+            save_metadata: false,
+            save_index: false,
+            overwrite_policy: OverwritePolicy::default(),
+            auto_save_options: AutoSaveOptions::default(),
+            theme: ThemePreference::System,
             dark_mode: true,
+            zoom_factor: 1.0,
             plot_options: PlotOptions::default(),
+            parsing_options: ParsingOptions::default(),
             raw_traffic_options: RawTrafficOptions::default(),
             record_options: RecordOptions::default(),
+            fft_options: FftOptions::default(),
+            readout_options: ReadoutOptions::default(),
+            alarm_options: AlarmOptions::default(),
             commands: vec![Command {
                 name: "Command 1".to_owned(),
                 cmd: "".to_owned(),
                 editing: false,
             }],
+            sequence: CommandSequence::default(),
+            send_file: SendFileOptions::default(),
+            tcp_address: "".to_string(),
+            break_duration_ms: 250,
+            clear_on_connect: false,
+            watchdog: false,
+            watchdog_seconds: 10.0,
+            history: vec![],
+            history_size: 100,
+            console_scrollback: DEFAULT_CONSOLE_SCROLLBACK,
         }
     }
 }
@@ -180,24 +344,96 @@ pub fn load_gui_settings() -> GuiSettingsContainer {
     });
     gui_settings.record_options.enable = false;
     gui_settings.record_options.record_path = PathBuf::new();
+    // the units used to be set in the readout
+    let readout_units = std::mem::take(&mut gui_settings.readout_options.units);
+    if gui_settings.plot_options.units.is_empty() {
+        gui_settings.plot_options.units = readout_units;
+    }
     gui_settings
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// The color theme of the GUI.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ThemePreference {
+    /// follow the theme of the OS, also when it changes at runtime
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl fmt::Display for ThemePreference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemePreference::System => write!(f, "System"),
+            ThemePreference::Light => write!(f, "Light"),
+            ThemePreference::Dark => write!(f, "Dark"),
+        }
+    }
+}
+
+impl ThemePreference {
+    pub const ALL: [ThemePreference; 3] = [
+        ThemePreference::System,
+        ThemePreference::Light,
+        ThemePreference::Dark,
+    ];
+
+    /// The visuals of the theme, `system` is the theme of the OS if it is known.
+    fn visuals(&self, system: Option<eframe::Theme>) -> Visuals {
+        match self {
+            ThemePreference::System => system.unwrap_or(eframe::Theme::Dark).egui_visuals(),
+            ThemePreference::Light => Visuals::light(),
+            ThemePreference::Dark => Visuals::dark(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum GuiTabs {
     RawTraffic,
     Commands,
     PlotOptions,
     Record,
+    Fft,
+    Readout,
+    HexDump,
+}
+
+/// Where the raw traffic is shown.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ViewLayout {
+    /// in its tab below the plots
+    #[default]
+    Tabs,
+    /// right of the plots, so that both are visible while using the other tabs
+    SideBySide,
+}
+
+/// How the number of stored raw traffic packets is limited.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum TrafficCap {
+    #[default]
+    Count,
+    /// total payload size, bounds the memory use for packets of any size
+    Bytes,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct RawTrafficOptions {
     pub enable: bool,
     show_sent_cmds: bool,
     show_timestamps: bool,
+    wall_clock_timestamps: bool,
+    send_hex: bool,
+    /// Enter sends the command, otherwise it starts a new line and only the button sends
+    send_on_enter: bool,
+    pub cap: TrafficCap,
     pub max_len: usize,
-    eol: String,
+    pub max_kb: usize,
+    /// remove ANSI escape sequences (e.g. colors) before the lines are shown and parsed
+    pub strip_ansi: bool,
 }
 
 impl Default for RawTrafficOptions {
@@ -206,26 +442,154 @@ impl Default for RawTrafficOptions {
             enable: false,
             show_sent_cmds: true,
             show_timestamps: true,
+            wall_clock_timestamps: false,
+            send_hex: false,
+            send_on_enter: true,
+            cap: TrafficCap::Count,
             max_len: 5000,
-            eol: "\\r\\n".to_owned(),
+            max_kb: 1024,
+            strip_ansi: true,
+        }
+    }
+}
+
+/// Filters the displayed lines of the raw traffic or the console, the data itself is kept.
+#[derive(Debug, Default)]
+pub struct TextFilter {
+    text: String,
+    regex_mode: bool,
+    /// the compiled `text` in regex mode, `None` if it is invalid
+    regex: Option<Regex>,
+    /// number of lines which matched the last time the filter was applied
+    match_count: usize,
+}
+
+impl TextFilter {
+    pub fn is_active(&self) -> bool {
+        !self.text.is_empty()
+    }
+
+    fn update_regex(&mut self) {
+        self.regex = if self.regex_mode {
+            Regex::new(&self.text).ok()
+        } else {
+            None
+        };
+    }
+
+    /// Case-insensitive substring match, or a regex match in regex mode.
+    pub fn matches(&self, line: &str) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        if self.regex_mode {
+            return self.regex.as_ref().is_some_and(|r| r.is_match(line));
         }
+        line.to_lowercase().contains(&self.text.to_lowercase())
     }
 }
 
+/// A column name which is being edited in place, in the names panel or over the plot legend.
+#[derive(Debug, Clone)]
+pub struct ColumnRename {
+    pub index: usize,
+    pub text: String,
+    /// where the editor is shown when the rename was started from the legend
+    pub popup_pos: Option<Pos2>,
+    /// the reason the last entered name was rejected
+    pub error: Option<String>,
+}
+
+/// Checks the new name of column `index`, it must not be empty or used by another column.
+fn validate_column_name(labels: &[String], index: usize, name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The name must not be empty.".to_string());
+    }
+    if labels
+        .iter()
+        .enumerate()
+        .any(|(i, label)| i != index && label == name)
+    {
+        return Err(format!("\"{name}\" is already used by another column."));
+    }
+    Ok(name.to_string())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 enum XAxisType {
+    /// seconds since the connection was opened
     Time,
+    /// wall-clock time of the host
+    AbsoluteTime,
     Point,
     FirstData,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+enum PlotMode {
+    TimeSeries,
+    /// one series against another (e.g. a Lissajous figure)
+    Xy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+enum YAxisScale {
+    Linear,
+    Log10,
+}
+
+/// How the y range of the plots is chosen.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+enum YAxisRange {
+    /// from the minimum to the maximum of the data
+    Full,
+    /// between two percentiles of the visible data, so that single spikes do not compress
+    /// the trace
+    Percentile,
+    /// fixed limits, or a fixed minimum or maximum
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct PlotOptions {
     pub buffer_size: usize,
     plotting_range: usize,
     labels: Vec<String>,
-    number_of_plots: usize,
+    /// shown with the name and the values of each series, e.g. `°C`, empty for none. Only
+    /// for display, the parsing and the saved files are not changed.
+    units: Vec<String>,
+    visible: Vec<bool>,
+    /// user chosen colors, `None` uses the automatic color of the plot
+    colors: Vec<Option<egui::Color32>>,
+    /// per series display gain and position, the data and the exports are not changed
+    scales: Vec<f64>,
+    offsets: Vec<f64>,
+    panels: Vec<PlotPanel>,
     x_axis: XAxisType,
+    y_axis: YAxisScale,
+    y_range: YAxisRange,
+    /// the percentile range shows from this to 100 minus this percentile
+    y_percentile: f64,
+    y_limits: [f64; 2],
+    /// the manual limits which are set, the others follow the data
+    y_locked: [bool; 2],
+    pub header_mode: bool,
+    plot_mode: PlotMode,
+    xy_x_series: usize,
+    xy_y_series: usize,
+    line_width: f32,
+    show_markers: bool,
+    /// vertical lines at the sent commands
+    show_command_markers: bool,
+    /// draw the captured reference behind the live data
+    show_reference: bool,
+    /// a small line of the latest samples next to each name
+    show_sparklines: bool,
+    /// upper bound for the drawn points per series, denser data is reduced to min/max pairs
+    max_plot_points: usize,
+    trigger: TriggerOptions,
 }
 
 impl Default for PlotOptions {
@@ -234,14 +598,196 @@ impl Default for PlotOptions {
             buffer_size: 5000,
             plotting_range: usize::MAX,
             labels: vec!["Column 0".to_string()],
-            number_of_plots: 1,
+            units: vec![],
+            visible: vec![true],
+            colors: vec![None],
+            scales: vec![1.0],
+            offsets: vec![0.0],
+            panels: vec![PlotPanel::default()],
             x_axis: XAxisType::Point,
+            y_axis: YAxisScale::Linear,
+            y_range: YAxisRange::Full,
+            y_percentile: 1.0,
+            y_limits: [0.0, 1.0],
+            y_locked: [true, true],
+            header_mode: false,
+            plot_mode: PlotMode::TimeSeries,
+            xy_x_series: 0,
+            xy_y_series: 1,
+            line_width: 1.0,
+            show_markers: false,
+            show_command_markers: true,
+            show_reference: true,
+            show_sparklines: true,
+            max_plot_points: 10_000,
+            trigger: TriggerOptions::default(),
         }
     }
 }
 
+/// One of the stacked plots. All panels show the same data, each with its own zoom, follow
+/// setting and series.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PlotPanel {
+    /// keeps the zoom of a panel when a panel above it is removed
+    id: usize,
+    /// only plot the last `follow_seconds` of the buffer, like a rolling scope display
+    follow: bool,
+    follow_seconds: f64,
+    /// the series shown in this panel, in addition to the visibility in the legend
+    series: Vec<bool>,
+    /// draw reduced series as the mean of each bucket in a shaded min/max band
+    envelope: bool,
+}
+
+impl Default for PlotPanel {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            follow: false,
+            follow_seconds: 10.0,
+            series: vec![true],
+            envelope: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TriggerEdge {
+    Rising,
+    Falling,
+}
+
+/// Freezes the buffer around the sample where a series crosses the level, like a scope.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TriggerOptions {
+    pub enable: bool,
+    pub series: usize,
+    pub level: f64,
+    pub edge: TriggerEdge,
+    /// samples kept before the trigger sample
+    pub pre_samples: usize,
+    /// samples captured after the trigger sample before the buffer is frozen
+    pub post_samples: usize,
+}
+
+impl Default for TriggerOptions {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            series: 0,
+            level: 0.0,
+            edge: TriggerEdge::Rising,
+            pre_samples: 100,
+            post_samples: 100,
+        }
+    }
+}
+
+/// The safe band of one series.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AlarmLimits {
+    pub enable: bool,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Default for AlarmLimits {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            low: 0.0,
+            high: 100.0,
+        }
+    }
+}
+
+/// Reports the series whose newest sample is outside of its limits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AlarmOptions {
+    /// one entry per series
+    pub limits: Vec<AlarmLimits>,
+    /// play a short tone on the default audio output when an alarm is raised
+    pub beep: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FftOptions {
+    series: usize,
+    fft_size: usize,
+    /// use `sample_rate` instead of the rate estimated from the time steps
+    manual_sample_rate: bool,
+    /// in Hz
+    sample_rate: f64,
+}
+
+impl Default for FftOptions {
+    fn default() -> Self {
+        Self {
+            series: 0,
+            fft_size: 1024,
+            manual_sample_rate: false,
+            sample_rate: 100.0,
+        }
+    }
+}
+
+/// The large display of the latest value of each series.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReadoutOptions {
+    decimals: usize,
+    text_size: f32,
+    /// the units of the series from before they moved to `PlotOptions`, only read to carry
+    /// them over
+    #[serde(skip_serializing)]
+    units: Vec<String>,
+}
+
+impl Default for ReadoutOptions {
+    fn default() -> Self {
+        Self {
+            decimals: 2,
+            text_size: 40.0,
+            units: vec![],
+        }
+    }
+}
+
+/// A named set of the device, parsing and plot settings, to switch between boards quickly.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    /// the port settings, the port itself is not changed when the profile is loaded
+    pub device: Device,
+    pub parsing_options: ParsingOptions,
+    pub plot_options: PlotOptions,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+}
+
+pub fn load_profiles() -> Profiles {
+    Profiles::load(&APP_INFO, PREFS_KEY_PROFILES).unwrap_or_default()
+}
+
+pub fn save_profiles(profiles: &Profiles) {
+    if profiles.save(&APP_INFO, PREFS_KEY_PROFILES).is_err() {
+        println!("failed to save profiles");
+    }
+}
+
 pub struct MyApp {
     connected_to_device: bool,
+    connection_status: ConnectionStatus,
+    source: SourceType,
     command: String,
     device: String,
     old_device: String,
@@ -255,75 +801,373 @@ pub struct MyApp {
     gui_conf: GuiSettingsContainer,
     print_lock: Arc<RwLock<Vec<Print>>>,
     device_lock: Arc<RwLock<Device>>,
-    devices_lock: Arc<RwLock<Vec<String>>>,
-    connected_lock: Arc<RwLock<bool>>,
+    devices_lock: Arc<RwLock<Vec<PortInfo>>>,
+    connected_lock: Arc<RwLock<ConnectionStatus>>,
     data_lock: Arc<RwLock<DataContainer>>,
-    send_tx: Sender<String>,
+    byte_log_lock: Arc<RwLock<ByteLog>>,
+    send_tx: Sender<SendPayload>,
+    control_tx: Sender<PortControl>,
+    dtr: bool,
+    rts: bool,
     gui_event_tx: Sender<GuiEvent>,
     record_options_tx: Sender<RecordOptions>,
-    history: Vec<String>,
     index: usize,
     save_raw: bool,
+    /// save only the samples in the x range of the plots
+    save_visible_range: bool,
+    /// when the running command sequence sends its last line
+    sequence_end: Option<Instant>,
+    /// a save to an existing file, waiting for the user to decide
+    pending_save: Option<FileOptions>,
+    /// a file dropped while connected, waiting for the user to decide
+    pending_load: Option<PathBuf>,
+    /// a file to be loaded once the serial thread has disconnected, so that no live data is
+    /// mixed into it
+    load_after_disconnect: Option<PathBuf>,
+    /// the x range shown by the plots in the last frame
+    plot_x_bounds: Option<(f64, f64)>,
+    /// the percentile y range of each plot panel (by id), recomputed every
+    /// `Y_RANGE_UPDATE_INTERVAL`
+    y_ranges: HashMap<usize, (Instant, [f64; 2])>,
     show_warning_window: WindowFeedback,
     do_not_show_clear_warning: bool,
     need_initialize: bool,
+    baud_text: String,
     right_panel_expanded: bool,
     active_tab: Option<GuiTabs>,
+    adopted_header: Option<Vec<String>>,
+    plot_paused: bool,
+    plot_resumed: bool,
+    /// min/max/mean/std of each series, recomputed every `STATS_UPDATE_INTERVAL`
+    stats: Vec<Option<SeriesStats>>,
+    stats_updated: Instant,
+    traffic_filter: TextFilter,
+    console_filter: TextFilter,
+    /// all connections, the slot of the active one is `None`
+    connections: Vec<Option<ConnectionState>>,
+    active_connection: usize,
+    /// the threads of all connections, stopped when the window is closed
+    threads: Vec<ConnectionThreads>,
+    /// keeps the FFT plans, so that they are not recomputed every frame
+    fft_planner: RealFftPlanner<f64>,
+    renaming: Option<ColumnRename>,
+    /// a snapshot of the data to compare the live data with, it is not trimmed
+    reference: Option<DataContainer>,
+    /// the offset and the bytes shown by the frozen hex dump
+    hex_dump_frozen: Option<(usize, Vec<u8>)>,
+    profiles: Profiles,
+    /// the name of the loaded profile, or of the one to be saved
+    profile_name: String,
+}
+
+/// The time without data after which the main thread raises an error, `None` if disabled.
+fn watchdog_timeout(gui_conf: &GuiSettingsContainer) -> Option<Duration> {
+    gui_conf
+        .watchdog
+        .then(|| Duration::from_secs_f64(gui_conf.watchdog_seconds.max(1.0)))
+}
+
+/// Sends the settings which are handled in the main thread of a connection.
+fn send_gui_settings(gui_event_tx: &Sender<GuiEvent>, gui_conf: &GuiSettingsContainer) {
+    gui_event_tx
+        .send(GuiEvent::SetRawTrafficOptions(
+            gui_conf.raw_traffic_options.clone(),
+        ))
+        .expect("Failed to send raw traffic options");
+    gui_event_tx
+        .send(GuiEvent::SetBufferSize(gui_conf.plot_options.buffer_size))
+        .expect("Failed to send buffer size");
+    gui_event_tx
+        .send(GuiEvent::SetDelimiter(gui_conf.parsing_options.delimiter))
+        .expect("Failed to send delimiter");
+    gui_event_tx
+        .send(GuiEvent::SetResetLimits(
+            gui_conf.parsing_options.reset_after_mismatches,
+            gui_conf.parsing_options.reset_stable_lines,
+        ))
+        .expect("Failed to send reset limits");
+    gui_event_tx
+        .send(GuiEvent::SetRegex(gui_conf.parsing_options.regex.clone()))
+        .expect("Failed to send regex");
+    gui_event_tx
+        .send(GuiEvent::SetParseHex(gui_conf.parsing_options.parse_hex))
+        .expect("Failed to send parse hex");
+    gui_event_tx
+        .send(GuiEvent::SetDecimalSeparator(
+            gui_conf.parsing_options.decimal_separator,
+        ))
+        .expect("Failed to send decimal separator");
+    gui_event_tx
+        .send(GuiEvent::SetMissingFields(
+            gui_conf.parsing_options.missing_fields,
+        ))
+        .expect("Failed to send missing fields");
+    gui_event_tx
+        .send(GuiEvent::SetWatchdog(watchdog_timeout(gui_conf)))
+        .expect("Failed to send watchdog");
+    gui_event_tx
+        .send(GuiEvent::SetComputed(
+            gui_conf.parsing_options.computed.clone(),
+        ))
+        .expect("Failed to send computed columns");
+    gui_event_tx
+        .send(GuiEvent::SetDeviceTime(
+            gui_conf.parsing_options.device_time,
+            gui_conf.parsing_options.time_column,
+        ))
+        .expect("Failed to send device time");
+    gui_event_tx
+        .send(GuiEvent::SetTrigger(gui_conf.plot_options.trigger.clone()))
+        .expect("Failed to send trigger");
+    gui_event_tx
+        .send(GuiEvent::SetAlarms(gui_conf.alarm_options.clone()))
+        .expect("Failed to send alarms");
+    gui_event_tx
+        .send(GuiEvent::SetHeaderMode(gui_conf.plot_options.header_mode))
+        .expect("Failed to send header mode");
+    gui_event_tx
+        .send(GuiEvent::SetSaveAbsoluteTime(gui_conf.save_absolute_time))
+        .expect("Failed to send save absolute time");
+    gui_event_tx
+        .send(GuiEvent::SetAutoSave(gui_conf.auto_save_options.clone()))
+        .expect("Failed to send auto-save options");
+}
+
+/// A connection that is currently not shown in the GUI. The shown connection lives
+/// in the fields of `MyApp`, switching connections swaps them with these.
+struct ConnectionState {
+    /// each connection has its own console
+    print_lock: Arc<RwLock<Vec<Print>>>,
+    plot_options: PlotOptions,
+    record_options: RecordOptions,
+    adopted_header: Option<Vec<String>>,
+    connected_to_device: bool,
+    source: SourceType,
+    device: String,
+    old_device: String,
+    device_idx: usize,
+    device_lock: Arc<RwLock<Device>>,
+    connected_lock: Arc<RwLock<ConnectionStatus>>,
+    data_lock: Arc<RwLock<DataContainer>>,
+    byte_log_lock: Arc<RwLock<ByteLog>>,
+    send_tx: Sender<SendPayload>,
+    control_tx: Sender<PortControl>,
+    dtr: bool,
+    rts: bool,
+    gui_event_tx: Sender<GuiEvent>,
+    record_options_tx: Sender<RecordOptions>,
+}
+
+impl ConnectionState {
+    fn new(
+        connection: Connection,
+        print_lock: Arc<RwLock<Vec<Print>>>,
+        plot_options: PlotOptions,
+    ) -> Self {
+        Self {
+            print_lock,
+            plot_options,
+            record_options: RecordOptions::default(),
+            adopted_header: None,
+            connected_to_device: false,
+            source: SourceType::Serial,
+            device: "".to_string(),
+            old_device: "".to_string(),
+            device_idx: 0,
+            device_lock: connection.device_lock,
+            connected_lock: connection.connected_lock,
+            data_lock: connection.data_lock,
+            byte_log_lock: connection.byte_log_lock,
+            send_tx: connection.send_tx,
+            control_tx: connection.control_tx,
+            dtr: true,
+            rts: true,
+            gui_event_tx: connection.gui_event_tx,
+            record_options_tx: connection.record_options_tx,
+        }
+    }
 }
 
 // #[allow(clippy::too_many_arguments)]
 impl MyApp {
     pub fn new(
         print_lock: Arc<RwLock<Vec<Print>>>,
-        data_lock: Arc<RwLock<DataContainer>>,
-        device_lock: Arc<RwLock<Device>>,
-        devices_lock: Arc<RwLock<Vec<String>>>,
+        devices_lock: Arc<RwLock<Vec<PortInfo>>>,
         devices: SerialDevices,
-        connected_lock: Arc<RwLock<bool>>,
-        gui_conf: GuiSettingsContainer,
-        send_tx: Sender<String>,
-        gui_event_tx: Sender<GuiEvent>,
-        record_options_tx: Sender<RecordOptions>,
+        mut gui_conf: GuiSettingsContainer,
+        connection: Connection,
+        threads: ConnectionThreads,
     ) -> Self {
-        gui_event_tx
-            .send(GuiEvent::SetRawTrafficOptions(
-                gui_conf.raw_traffic_options.clone(),
-            ))
-            .expect("Failed to send raw traffic options");
-        gui_event_tx
-            .send(GuiEvent::SetBufferSize(gui_conf.plot_options.buffer_size))
-            .expect("Failed to send buffer size");
+        set_console_scrollback(&print_lock, gui_conf.console_scrollback);
+        if let Err(e) = gui_conf.parsing_options.compile_regex() {
+            print_to_console(&print_lock, Print::Error(format!("invalid regex: {e}")));
+        }
+        if let Err(e) = gui_conf.parsing_options.compile_computed() {
+            print_to_console(
+                &print_lock,
+                Print::Error(format!("invalid expression: {e}")),
+            );
+        }
+        send_gui_settings(&connection.gui_event_tx, &gui_conf);
+        let history_len = gui_conf.history.len();
+        // preselect the last used device, nothing is selected if its settings are gone
+        let (device_idx, device) = match devices
+            .devices
+            .iter()
+            .position(|d| !gui_conf.device.is_empty() && d.name == gui_conf.device)
+        {
+            Some(idx) => (idx, gui_conf.device.clone()),
+            None => (0, "".to_string()),
+        };
+        let plot_serial_display_ratio = gui_conf.plot_serial_display_ratio;
+        let right_panel_expanded = gui_conf.right_panel_expanded;
+        let active_tab = gui_conf.active_tab;
         Self {
             connected_to_device: false,
+            connection_status: ConnectionStatus::default(),
+            source: SourceType::Serial,
             picked_path: PathBuf::new(),
-            device: "".to_string(),
+            device,
             old_device: "".to_string(),
             data: DataContainer::default(),
             console: vec![Print::Message(
                 "waiting for serial connection..,".to_owned(),
             )],
-            connected_lock,
-            device_lock,
+            connected_lock: connection.connected_lock,
+            device_lock: connection.device_lock,
             devices_lock,
-            device_idx: 0,
+            device_idx,
             serial_devices: devices,
             print_lock,
             gui_conf,
-            data_lock,
-            send_tx,
-            gui_event_tx,
-            plot_serial_display_ratio: 0.75,
+            data_lock: connection.data_lock,
+            byte_log_lock: connection.byte_log_lock,
+            send_tx: connection.send_tx,
+            control_tx: connection.control_tx,
+            dtr: true,
+            rts: true,
+            gui_event_tx: connection.gui_event_tx,
+            plot_serial_display_ratio,
             command: "".to_string(),
             save_raw: false,
-            history: vec![],
-            index: 0,
+            save_visible_range: false,
+            pending_save: None,
+            pending_load: None,
+            load_after_disconnect: None,
+            sequence_end: None,
+            plot_x_bounds: None,
+            y_ranges: HashMap::new(),
+            index: history_len,
             plot_location: None,
             do_not_show_clear_warning: false,
             show_warning_window: WindowFeedback::None,
             need_initialize: false,
-            right_panel_expanded: true,
-            active_tab: Some(GuiTabs::PlotOptions),
-            record_options_tx,
+            baud_text: "".to_string(),
+            right_panel_expanded,
+            active_tab,
+            adopted_header: None,
+            plot_paused: false,
+            plot_resumed: false,
+            stats: vec![],
+            stats_updated: Instant::now(),
+            traffic_filter: TextFilter::default(),
+            console_filter: TextFilter::default(),
+            record_options_tx: connection.record_options_tx,
+            connections: vec![None],
+            active_connection: 0,
+            threads: vec![threads],
+            fft_planner: RealFftPlanner::new(),
+            renaming: None,
+            reference: None,
+            hex_dump_frozen: None,
+            profiles: load_profiles(),
+            profile_name: "".to_string(),
+        }
+    }
+
+    /// Spawns the threads of a new connection and shows it. It starts with the plot settings
+    /// of the shown connection, but without its column names.
+    fn add_connection(&mut self) {
+        let print_lock = Arc::new(RwLock::new(vec![Print::Empty]));
+        let (connection, threads) = spawn_connection(&self.devices_lock, &print_lock);
+        send_gui_settings(&connection.gui_event_tx, &self.gui_conf);
+        let plot_options = PlotOptions {
+            labels: PlotOptions::default().labels,
+            ..self.gui_conf.plot_options.clone()
+        };
+        self.threads.push(threads);
+        self.connections.push(Some(ConnectionState::new(
+            connection,
+            print_lock,
+            plot_options,
+        )));
+        self.switch_connection(self.connections.len() - 1);
+    }
+
+    /// Stops the threads of a connection and removes it, the last connection is kept.
+    fn close_connection(&mut self, idx: usize) {
+        if self.connections.len() < 2 {
+            return;
+        }
+        if idx == self.active_connection {
+            self.switch_connection(if idx == 0 { 1 } else { idx - 1 });
+        }
+        // dropping the state closes the channels to the threads
+        self.connections.remove(idx);
+        if self.active_connection > idx {
+            self.active_connection -= 1;
+        }
+        self.threads.remove(idx).stop();
+    }
+
+    fn switch_connection(&mut self, idx: usize) {
+        if idx == self.active_connection {
+            return;
+        }
+        if let Some(mut state) = self.connections[idx].take() {
+            std::mem::swap(&mut self.print_lock, &mut state.print_lock);
+            std::mem::swap(&mut self.gui_conf.plot_options, &mut state.plot_options);
+            std::mem::swap(&mut self.gui_conf.record_options, &mut state.record_options);
+            std::mem::swap(&mut self.adopted_header, &mut state.adopted_header);
+            std::mem::swap(
+                &mut self.connected_to_device,
+                &mut state.connected_to_device,
+            );
+            std::mem::swap(&mut self.source, &mut state.source);
+            std::mem::swap(&mut self.device, &mut state.device);
+            std::mem::swap(&mut self.old_device, &mut state.old_device);
+            std::mem::swap(&mut self.device_idx, &mut state.device_idx);
+            std::mem::swap(&mut self.device_lock, &mut state.device_lock);
+            std::mem::swap(&mut self.connected_lock, &mut state.connected_lock);
+            std::mem::swap(&mut self.data_lock, &mut state.data_lock);
+            std::mem::swap(&mut self.byte_log_lock, &mut state.byte_log_lock);
+            std::mem::swap(&mut self.send_tx, &mut state.send_tx);
+            std::mem::swap(&mut self.control_tx, &mut state.control_tx);
+            std::mem::swap(&mut self.dtr, &mut state.dtr);
+            std::mem::swap(&mut self.rts, &mut state.rts);
+            std::mem::swap(&mut self.gui_event_tx, &mut state.gui_event_tx);
+            std::mem::swap(&mut self.record_options_tx, &mut state.record_options_tx);
+            self.connections[self.active_connection] = Some(state);
+            self.active_connection = idx;
+            self.show_warning_window = WindowFeedback::None;
+            self.renaming = None;
+            self.hex_dump_frozen = None;
+        }
+    }
+
+    fn connection_label(&self, idx: usize) -> String {
+        let device = if idx == self.active_connection {
+            self.device.as_str()
+        } else {
+            match &self.connections[idx] {
+                Some(state) => state.device.as_str(),
+                None => "",
+            }
+        };
+        if device.is_empty() {
+            format!("Connection {}", idx + 1)
+        } else {
+            device.replace("/dev/tty.", "")
         }
     }
 
@@ -357,30 +1201,195 @@ impl MyApp {
         window_feedback
     }
 
+    /// Asks whether an existing file should be overwritten, the save is sent once decided.
+    fn overwrite_prompt_window(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.pending_save else {
+            return;
+        };
+        let mut decision = None;
+        egui::Window::new("File exists")
+            .anchor(Align2::CENTER_CENTER, Vec2 { x: 0.0, y: 0.0 })
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(10.0);
+                    ui.label(format!("{:?} already exists.", pending.file_path));
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Overwrite").clicked() {
+                            decision = Some(Some(OverwritePolicy::Overwrite));
+                        }
+                        if ui
+                            .button("Save as New File")
+                            .on_hover_text("Save with a numbered suffix, e.g. data_001.csv.")
+                            .clicked()
+                        {
+                            decision = Some(Some(OverwritePolicy::RenameIfExists));
+                        }
+                        if ui.button("Cancel").clicked() {
+                            decision = Some(None);
+                        }
+                    });
+                });
+            });
+        if let Some(policy) = decision {
+            let pending = self.pending_save.take();
+            if let (Some(policy), Some(file_options)) = (policy, pending) {
+                self.send_save(FileOptions {
+                    overwrite: policy,
+                    ..file_options
+                });
+            }
+        }
+    }
+
+    /// Loads a dropped CSV file, while connected only after asking whether to replace the
+    /// live data.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+        for path in dropped {
+            let is_csv = path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+            if !is_csv {
+                print_to_console(
+                    &self.print_lock,
+                    Print::Error(format!("only CSV files can be loaded, not {:?}", path)),
+                );
+            } else if self.pending_load.is_some() || self.load_after_disconnect.is_some() {
+                print_to_console(
+                    &self.print_lock,
+                    Print::Error(format!(
+                        "another file is waiting to be loaded, ignoring {:?}",
+                        path
+                    )),
+                );
+            } else if self.connected_to_device {
+                self.pending_load = Some(path);
+            } else {
+                self.load_csv(&path);
+            }
+        }
+
+        if !self.connected_to_device {
+            if let Some(path) = self.load_after_disconnect.take() {
+                self.load_csv(&path);
+            }
+        }
+        let Some(path) = &self.pending_load else {
+            return;
+        };
+        let mut decision = None;
+        egui::Window::new("Load file")
+            .anchor(Align2::CENTER_CENTER, Vec2 { x: 0.0, y: 0.0 })
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(10.0);
+                    ui.label(format!(
+                        "Disconnect from the device and replace the live data with {:?}?",
+                        path
+                    ));
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Disconnect and replace").clicked() {
+                            decision = Some(true);
+                        }
+                        if ui.button("Ignore").clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+            });
+        if let Some(replace) = decision {
+            let path = self.pending_load.take();
+            if let (true, Some(path)) = (replace, path) {
+                // the file is loaded once the port is closed, see above
+                if let Ok(mut device) = self.device_lock.write() {
+                    device.name.clear();
+                }
+                self.load_after_disconnect = Some(path);
+            }
+        }
+    }
+
     fn console_text(&self, packet: &crate::data::Packet) -> Option<String> {
+        let timestamp = if self.gui_conf.raw_traffic_options.wall_clock_timestamps {
+            format_time_of_day(packet.absolute_time)
+        } else {
+            format!("t + {:.3}s", packet.relative_time as f32 / 1000.0)
+        };
         match (
             self.gui_conf.raw_traffic_options.show_sent_cmds,
             self.gui_conf.raw_traffic_options.show_timestamps,
             &packet.direction,
         ) {
             (true, true, _) => Some(format!(
-                "[{}] t + {:.3}s: {}\n",
-                packet.direction,
-                packet.relative_time as f32 / 1000.0,
-                packet.payload
+                "[{}] {}: {}\n",
+                packet.direction, timestamp, packet.payload
             )),
             (true, false, _) => Some(format!("[{}]: {}\n", packet.direction, packet.payload)),
-            (false, true, SerialDirection::Receive) => Some(format!(
-                "t + {:.3}s: {}\n",
-                packet.relative_time as f32 / 1000.0,
-                packet.payload
-            )),
+            (false, true, SerialDirection::Receive) => {
+                Some(format!("{}: {}\n", timestamp, packet.payload))
+            }
             (false, false, SerialDirection::Receive) => Some(packet.payload.clone() + "\n"),
             (_, _, _) => None,
         }
     }
 
+    fn draw_status_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Received: {} in {} lines",
+                    format_bytes(self.data.received_bytes as f64),
+                    self.data.received_lines
+                ));
+                ui.separator();
+                ui.label(format!("{}/s", format_bytes(self.data.bytes_per_second)));
+                ui.separator();
+                ui.label(format!("{:.1} samples/s", self.data.samples_per_second));
+                if let Some(quality) = self.data.parse_quality {
+                    ui.separator();
+                    let text = format!("Parse quality: {:.0}%", quality * 100.0);
+                    let label = if quality < 0.9 {
+                        ui.colored_label(egui::Color32::RED, text)
+                    } else {
+                        ui.label(text)
+                    };
+                    label.on_hover_text(
+                        "Share of the latest received lines which were parsed into a sample. \
+                        A low value points to a wrong delimiter or framing.",
+                    );
+                }
+            });
+        });
+    }
+
     fn draw_central_panel(&mut self, ctx: &egui::Context) {
+        let side_by_side = self.gui_conf.view_layout == ViewLayout::SideBySide;
+        if side_by_side {
+            if self.active_tab == Some(GuiTabs::RawTraffic) {
+                self.active_tab = Some(GuiTabs::PlotOptions);
+            }
+            let panel = SidePanel::right("raw traffic split")
+                .default_width(self.gui_conf.raw_traffic_width)
+                .width_range(200.0..=ctx.screen_rect().width() * 0.6)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    ui.add_space(5.0);
+                    self.serial_raw_traffic_ui(ui);
+                });
+            self.gui_conf.raw_traffic_width = panel.response.rect.width();
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             let panel_height = ui.available_size().y;
             let spacing = 5.0;
@@ -420,22 +1429,48 @@ impl MyApp {
                             self.active_tab = None
                         };
 
+                        if !side_by_side
+                            && ui
+                                .selectable_value(
+                                    &mut self.active_tab,
+                                    Some(GuiTabs::RawTraffic),
+                                    "Raw Traffic",
+                                )
+                                .double_clicked()
+                        {
+                            self.active_tab = None
+                        };
+
                         if ui
                             .selectable_value(
                                 &mut self.active_tab,
-                                Some(GuiTabs::RawTraffic),
-                                "Raw Traffic",
+                                Some(GuiTabs::Commands),
+                                "Commands",
                             )
                             .double_clicked()
                         {
                             self.active_tab = None
                         };
 
+                        if ui
+                            .selectable_value(&mut self.active_tab, Some(GuiTabs::Record), "Record")
+                            .double_clicked()
+                        {
+                            self.active_tab = None
+                        };
+
+                        if ui
+                            .selectable_value(&mut self.active_tab, Some(GuiTabs::Fft), "FFT")
+                            .double_clicked()
+                        {
+                            self.active_tab = None
+                        };
+
                         if ui
                             .selectable_value(
                                 &mut self.active_tab,
-                                Some(GuiTabs::Commands),
-                                "Commands",
+                                Some(GuiTabs::Readout),
+                                "Readout",
                             )
                             .double_clicked()
                         {
@@ -443,13 +1478,32 @@ impl MyApp {
                         };
 
                         if ui
-                            .selectable_value(&mut self.active_tab, Some(GuiTabs::Record), "Record")
+                            .selectable_value(
+                                &mut self.active_tab,
+                                Some(GuiTabs::HexDump),
+                                "Hex Dump",
+                            )
                             .double_clicked()
                         {
                             self.active_tab = None
                         };
 
-                        ui.add_space(ui.available_width() - 25.0);
+                        ui.add_space(ui.available_width() - 55.0);
+
+                        if ui
+                            .selectable_label(
+                                side_by_side,
+                                egui::RichText::new(egui_phosphor::regular::COLUMNS),
+                            )
+                            .on_hover_text("Show the raw traffic right of the plots.")
+                            .clicked()
+                        {
+                            self.gui_conf.view_layout = if side_by_side {
+                                ViewLayout::Tabs
+                            } else {
+                                ViewLayout::SideBySide
+                            };
+                        }
 
                         if ui
                             .selectable_label(
@@ -487,6 +1541,15 @@ impl MyApp {
                                 GuiTabs::Record => {
                                     self.record_gui(ui);
                                 }
+                                GuiTabs::Fft => {
+                                    self.fft_ui(ui);
+                                }
+                                GuiTabs::Readout => {
+                                    self.readout_ui(ui);
+                                }
+                                GuiTabs::HexDump => {
+                                    self.hex_dump_ui(ui);
+                                }
                             }
                         }
                         None => (),
@@ -498,15 +1561,16 @@ impl MyApp {
     }
 
     fn draw_side_panel(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::SidePanel::show_animated_between(
+        let panel = egui::SidePanel::show_animated_between(
             ctx,
             self.right_panel_expanded,
             SidePanel::right("settings panel collapsed")
                 .min_width(0.0)
                 .resizable(false),
             SidePanel::right("settings panel expanded")
-                .exact_width(RIGHT_PANEL_WIDTH)
-                .resizable(false),
+                .default_width(self.gui_conf.right_panel_width)
+                .width_range(MIN_PANEL_WIDTH..=MAX_PANEL_WIDTH)
+                .resizable(true),
             |ui, how_expanded| {
                 // ui.set_visible(true);
                 if how_expanded == 0.0 {
@@ -529,6 +1593,42 @@ impl MyApp {
                             self.right_panel_expanded = false;
                         };
                         self.paint_connection_indicator(ui);
+                        if let Some(uptime) = self.connection_uptime() {
+                            ui.label(uptime);
+                        }
+                    });
+                    ui.add_space(5.0);
+                    ui.horizontal_wrapped(|ui| {
+                        let mut close = None;
+                        for idx in 0..self.connections.len() {
+                            if ui
+                                .selectable_label(
+                                    idx == self.active_connection,
+                                    self.connection_label(idx),
+                                )
+                                .clicked()
+                            {
+                                self.switch_connection(idx);
+                            }
+                            if self.connections.len() > 1
+                                && ui
+                                    .small_button(egui_phosphor::regular::X)
+                                    .on_hover_text("Close the connection.")
+                                    .clicked()
+                            {
+                                close = Some(idx);
+                            }
+                        }
+                        if let Some(idx) = close {
+                            self.close_connection(idx);
+                        }
+                        if ui
+                            .button(egui::RichText::new(egui_phosphor::regular::PLUS))
+                            .on_hover_text("Add a connection.")
+                            .clicked()
+                        {
+                            self.add_connection();
+                        }
                     });
                     ui.add_space(5.0);
                     egui::ScrollArea::vertical()
@@ -538,19 +1638,48 @@ impl MyApp {
                             self.serial_settings_ui(ui, ctx);
                             ui.add_space(15.0);
                             self.plot_settings_ui(ui, ctx);
+                            ui.add_space(15.0);
+                            self.statistics_ui(ui);
                             ui.add_space(20.0);
                             ui.separator();
                             self.debug_console_ui(ui);
                         });
                 }
+                how_expanded
             },
         );
+        // remember the width chosen by dragging the edge of the panel
+        if let Some(panel) = panel {
+            if panel.inner == 1.0 {
+                self.gui_conf.right_panel_width = panel.response.rect.width();
+            }
+        }
+    }
+
+    /// How long the connection is up and how often it was reopened automatically, e.g.
+    /// "Connected 00:14:32, 2 reconnects".
+    fn connection_uptime(&self) -> Option<String> {
+        let since = self.connection_status.since?;
+        let seconds = since.elapsed().as_secs();
+        let reconnects = match self.connection_status.reconnects {
+            1 => "1 reconnect".to_string(),
+            n => format!("{n} reconnects"),
+        };
+        Some(format!(
+            "Connected {:02}:{:02}:{:02}, {reconnects}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        ))
     }
 
     fn paint_connection_indicator(&self, ui: &mut egui::Ui) {
         let (color, color_stroke) = if !self.connected_to_device {
             ui.add(egui::Spinner::new());
             (egui::Color32::DARK_RED, egui::Color32::RED)
+        } else if self.data.stalled {
+            // connected, but the watchdog did not see any data
+            (egui::Color32::from_rgb(160, 110, 0), egui::Color32::YELLOW)
         } else {
             (egui::Color32::DARK_GREEN, egui::Color32::GREEN)
         };
@@ -568,15 +1697,80 @@ impl MyApp {
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         if let Ok(read_guard) = self.connected_lock.read() {
-            self.connected_to_device = *read_guard;
+            self.connection_status = *read_guard;
+            self.connected_to_device = read_guard.connected;
         }
 
         if let Ok(read_guard) = self.data_lock.read() {
             self.data = read_guard.clone();
         }
 
+        // the system theme is re-checked every frame to follow changes of the OS
+        let visuals = self.gui_conf.theme.visuals(frame.info().system_theme);
+        if ctx.style().visuals.dark_mode != visuals.dark_mode {
+            ctx.set_visuals(visuals);
+        }
+        self.gui_conf.dark_mode = ctx.style().visuals.dark_mode;
+        self.gui_conf.zoom_factor = ctx.zoom_factor();
+
+        // new series are visible by default and use the automatic colors, unscaled
+        self.gui_conf
+            .plot_options
+            .visible
+            .resize(self.data.names.len(), true);
+        self.gui_conf
+            .plot_options
+            .colors
+            .resize(self.data.names.len(), None);
+        self.gui_conf
+            .plot_options
+            .scales
+            .resize(self.data.names.len(), 1.0);
+        self.gui_conf
+            .plot_options
+            .offsets
+            .resize(self.data.names.len(), 0.0);
+        self.gui_conf
+            .alarm_options
+            .limits
+            .resize(self.data.names.len(), AlarmLimits::default());
+        self.gui_conf
+            .plot_options
+            .units
+            .resize(self.data.names.len(), "".to_string());
+        if self.gui_conf.plot_options.panels.is_empty() {
+            self.gui_conf.plot_options.panels.push(PlotPanel::default());
+        }
+        for panel in self.gui_conf.plot_options.panels.iter_mut() {
+            panel.series.resize(self.data.names.len(), true);
+        }
+
+        // take over the names from a newly received header line or the regex groups
+        if (self.gui_conf.plot_options.header_mode || self.gui_conf.parsing_options.regex_mode)
+            && self.data.header.is_some()
+            && self.data.header != self.adopted_header
+        {
+            self.adopted_header = self.data.header.clone();
+            self.gui_conf.plot_options.labels = self.data.header.clone().unwrap_or_default();
+        }
+
+        // the queued data is recorded before the window closes
+        if ctx.input(|i| i.viewport().close_requested()) {
+            for threads in self.threads.drain(..) {
+                threads.stop();
+            }
+        }
+        // the channels of the stopped threads are closed, nothing is drawn (and sent) anymore
+        // until the window is gone
+        if self.threads.is_empty() {
+            return;
+        }
+
+        self.draw_status_bar(ctx);
         self.draw_side_panel(ctx, frame);
         self.draw_central_panel(ctx);
+        self.overwrite_prompt_window(ctx);
+        self.handle_dropped_files(ctx);
         ctx.request_repaint();
 
         self.gui_conf.x = ctx.used_size().x;
@@ -594,28 +1788,33 @@ impl eframe::App for MyApp {
 
         if let (Some(screenshot), Some(plot_location)) = (screenshot, self.plot_location) {
             let cwd = std::env::current_dir().unwrap_or_default();
+            let file_name = format!("plot_{}.png", Local::now().format("%Y%m%d_%H%M%S"));
             if let Some(mut path) = rfd::FileDialog::new()
                 .set_directory(cwd)
-                .set_file_name("plot.png")
+                .set_file_name(file_name)
                 .save_file()
             {
+                // the image is always written as png, the messages show the actual file
                 path.set_extension("png");
-
                 // for a full size application, we should put this in a different thread,
                 // so that the GUI doesn't lag during saving
 
                 let pixels_per_point = ctx.pixels_per_point();
                 let plot = screenshot.region(&plot_location, Some(pixels_per_point));
-                // save the plot to png
-                image::save_buffer(
-                    &path,
-                    plot.as_raw(),
-                    plot.width() as u32,
-                    plot.height() as u32,
-                    image::ColorType::Rgba8,
-                )
-                .unwrap();
-                eprintln!("Image saved to {path:?}.");
+                let image_options = ImageOptions { file_path: path };
+                match save_image(&plot, &image_options) {
+                    Ok(_) => print_to_console(
+                        &self.print_lock,
+                        Print::Ok(format!("saved plot to {:?} ", image_options.file_path)),
+                    ),
+                    Err(e) => print_to_console(
+                        &self.print_lock,
+                        Print::Error(format!(
+                            "failed to save plot to {:?}: {:?}",
+                            image_options.file_path, e
+                        )),
+                    ),
+                }
             }
         }
 
@@ -624,6 +1823,13 @@ impl eframe::App for MyApp {
 
     fn save(&mut self, _storage: &mut dyn Storage) {
         save_serial_settings(&self.serial_devices);
+        self.gui_conf.active_tab = self.active_tab;
+        self.gui_conf.right_panel_expanded = self.right_panel_expanded;
+        self.gui_conf.plot_serial_display_ratio = self.plot_serial_display_ratio;
+        self.gui_conf.device = self.device.clone();
+        if let Some(device) = self.serial_devices.devices.get(self.device_idx) {
+            self.gui_conf.baud = device.baud_rate;
+        }
         if let Err(err) = self.gui_conf.save(&APP_INFO, PREFS_KEY) {
             println!("gui settings save failed: {:?}", err);
         }
@@ -1,27 +1,280 @@
 use std::ops::RangeInclusive;
+use std::path::Path;
 
-use eframe::egui::{self, global_dark_light_mode_buttons, Button, TextEdit, TextStyle, Visuals};
-use egui_plot::{log_grid_spacer, Legend, Line, Plot, PlotPoint, PlotPoints};
+use eframe::egui::text::TextFormat;
+use eframe::egui::{self, Button, TextEdit, TextStyle};
+use egui_plot::{
+    log_grid_spacer, GridMark, Legend, Line, LineStyle, Plot, PlotBounds, PlotPoint, PlotPoints,
+    Points, Polygon, Text, VLine,
+};
 use rfd::MessageDialog;
 use serialport::{DataBits, FlowControl, Parity, StopBits};
 
-use crate::serial::clear_serial_settings;
+use crate::computed::ComputedColumn;
+use crate::data::{format_clock_time, Marker};
+use crate::fft::{estimate_sample_rate, magnitude_spectrum};
+use crate::frame::FieldType;
+use crate::io::{load_from_csv, OverwritePolicy};
+use crate::parser::{DecimalSeparator, Delimiter, MissingFields};
+use crate::serial::{
+    clear_serial_settings, contains_device, parse_hex, ByteLog, Framing, LineEnding, PortControl,
+    SourceType, HEX_DUMP_ROW_BYTES, TCP_PREFIX,
+};
 use crate::toggle::toggle;
 use crate::FileOptions;
 
 use super::*;
 
+/// Id of the text field used to rename a column, there is only one at a time.
+const RENAME_ID: &str = "column-rename";
+
+/// A change of the plot panels requested in the toolbar of a panel.
+enum PanelEdit {
+    /// add a panel below
+    Add,
+    Remove,
+}
+
+/// Id of the plot items of a series, to find the series of a hovered legend entry.
+fn series_id(i: usize) -> egui::Id {
+    egui::Id::new(("series", i))
+}
+
+/// Sends a saved command, `\r` and `\n` are replaced by the control characters.
+fn send_command(send_tx: &Sender<SendPayload>, print_lock: &Arc<RwLock<Vec<Print>>>, cmd: &str) {
+    let send_cmd = cmd.replace("\\r", "\r").replace("\\n", "\n");
+    if let Err(err) = send_tx.send(SendPayload::Text(send_cmd)) {
+        print_to_console(
+            print_lock,
+            Print::Error(format!("send_tx thread send failed: {:?}", err)),
+        );
+    }
+}
+
+/// The search field of a `TextFilter` with a regex toggle, a clear button and the match count.
+fn filter_ui(ui: &mut egui::Ui, filter: &mut TextFilter, width: f32) {
+    ui.horizontal(|ui| {
+        ui.label(egui_phosphor::regular::MAGNIFYING_GLASS);
+        let text_changed = ui
+            .add(
+                egui::TextEdit::singleline(&mut filter.text)
+                    .desired_width(width)
+                    .hint_text("Filter"),
+            )
+            .changed();
+        let regex_changed = ui
+            .checkbox(&mut filter.regex_mode, "Regex")
+            .on_hover_text("Match a regular expression instead of a case-insensitive text.")
+            .changed();
+        if text_changed || regex_changed {
+            filter.update_regex();
+        }
+        if ui
+            .button(egui_phosphor::regular::X)
+            .on_hover_text("Clear the filter.")
+            .clicked()
+        {
+            filter.text.clear();
+            filter.update_regex();
+        }
+        if filter.regex_mode && filter.is_active() && filter.regex.is_none() {
+            ui.colored_label(egui::Color32::RED, "invalid regex");
+        } else if filter.is_active() {
+            ui.label(format!("{} matches", filter.match_count));
+        }
+    });
+}
+
+/// The color egui_plot assigns to the i-th line (if all lines are shown).
+fn auto_color(i: usize) -> egui::Color32 {
+    let golden_ratio = (5.0_f32.sqrt() - 1.0) / 2.0;
+    let h = i as f32 * golden_ratio;
+    egui::ecolor::Hsva::new(h, 0.85, 0.5, 1.0).into()
+}
+
+/// number of the latest samples drawn in a sparkline
+const SPARKLINE_SAMPLES: usize = 100;
+
+/// A tiny line of the latest samples of a series, scaled to their own min/max.
+fn sparkline(ui: &mut egui::Ui, values: &[f64], color: egui::Color32) {
+    let (rect, _) = ui.allocate_exact_size(Vec2 { x: 60.0, y: 16.0 }, Sense::hover());
+    let values: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if values.len() < 2 {
+        return;
+    }
+    let (min, max) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+    let range = if max > min { max - min } else { 1.0 };
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| Pos2 {
+            x: rect.left() + rect.width() * i as f32 / (values.len() - 1) as f32,
+            y: rect.bottom() - rect.height() * ((v - min) / range) as f32,
+        })
+        .collect();
+    ui.painter()
+        .add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+}
+
+/// One row of the hex dump: the offset, the bytes in hex and the printable ASCII characters.
+fn hex_dump_row(offset: usize, bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+    let ascii: String = bytes
+        .iter()
+        .map(|b| {
+            if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!(
+        "{offset:08X}  {:<width$}  |{ascii}|",
+        hex.join(" "),
+        width = HEX_DUMP_ROW_BYTES * 3 - 1
+    )
+}
+
+/// The samples shown in the plots, for saving only the visible range.
+enum VisibleRange {
+    /// the x-axis can not be mapped to samples (or nothing is plotted yet)
+    Unsupported,
+    /// no samples are shown
+    Empty,
+    /// the time range (in ms) of the shown samples
+    Time(u128, u128),
+}
+
+/// Splits a graph at non-finite points, so that NaN and Inf samples show up as gaps.
+fn line_segments(graph: &[PlotPoint]) -> impl Iterator<Item = &[PlotPoint]> {
+    graph
+        .split(|p| !p.x.is_finite() || !p.y.is_finite())
+        .filter(|segment| !segment.is_empty())
+}
+
+/// Reduces a graph to at most `max_points` points by keeping the minimum and maximum of each
+/// bucket, so that spikes stay visible. A bucket containing a non-finite value keeps a gap.
+fn decimate(graph: Vec<PlotPoint>, max_points: usize) -> Vec<PlotPoint> {
+    if graph.len() <= max_points || max_points < 2 {
+        return graph;
+    }
+    let buckets = max_points / 2;
+    let bucket_size = graph.len().div_ceil(buckets);
+    let mut decimated = Vec::with_capacity(2 * buckets);
+    for bucket in graph.chunks(bucket_size) {
+        if let Some(gap) = bucket.iter().find(|p| !p.y.is_finite()) {
+            decimated.push(*gap);
+            continue;
+        }
+        let (mut min, mut max) = (bucket[0], bucket[0]);
+        for p in bucket {
+            if p.y < min.y {
+                min = *p;
+            }
+            if p.y > max.y {
+                max = *p;
+            }
+        }
+        if min.x <= max.x {
+            decimated.extend([min, max]);
+        } else {
+            decimated.extend([max, min]);
+        }
+    }
+    decimated
+}
+
+/// Reduces a graph like `decimate`, but to the mean of each bucket, together with the band
+/// between the minima and maxima of the buckets as one polygon per gap-free segment. `None` if
+/// the graph does not need to be reduced.
+fn envelope(
+    graph: &[PlotPoint],
+    max_points: usize,
+) -> Option<(Vec<PlotPoint>, Vec<Vec<PlotPoint>>)> {
+    if graph.len() <= max_points || max_points < 2 {
+        return None;
+    }
+    let bucket_size = graph.len().div_ceil(max_points / 2);
+    let mut means = vec![];
+    let mut bands = vec![];
+    // the x, min and max of the buckets since the last gap
+    let mut segment: Vec<(f64, f64, f64)> = vec![];
+    let close_band = |segment: &mut Vec<(f64, f64, f64)>, bands: &mut Vec<Vec<PlotPoint>>| {
+        if segment.len() > 1 {
+            // along the maxima and back along the minima
+            let upper = segment.iter().map(|&(x, _, max)| PlotPoint { x, y: max });
+            let lower = segment
+                .iter()
+                .rev()
+                .map(|&(x, min, _)| PlotPoint { x, y: min });
+            bands.push(upper.chain(lower).collect());
+        }
+        segment.clear();
+    };
+    for bucket in graph.chunks(bucket_size) {
+        if let Some(gap) = bucket.iter().find(|p| !p.y.is_finite()) {
+            means.push(*gap);
+            close_band(&mut segment, &mut bands);
+            continue;
+        }
+        let n = bucket.len() as f64;
+        let x = bucket.iter().map(|p| p.x).sum::<f64>() / n;
+        let mean = bucket.iter().map(|p| p.y).sum::<f64>() / n;
+        let min = bucket.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
+        let max = bucket.iter().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
+        means.push(PlotPoint { x, y: mean });
+        segment.push((x, min, max));
+    }
+    close_band(&mut segment, &mut bands);
+    Some((means, bands))
+}
+
+/// The range from the `percentile` to the `100 - percentile` of the values with a small
+/// margin, `None` without finite values.
+fn percentile_range(mut values: Vec<f64>, percentile: f64) -> Option<[f64; 2]> {
+    values.retain(|v| v.is_finite());
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let last = (values.len() - 1) as f64;
+    let fraction = percentile.clamp(0.0, 50.0) / 100.0;
+    let min = values[(last * fraction).round() as usize];
+    let max = values[(last * (1.0 - fraction)).round() as usize];
+    let margin = if max > min { (max - min) * 0.05 } else { 0.5 };
+    Some([min - margin, max + margin])
+}
+
 impl MyApp {
     pub fn serial_settings_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         self.need_initialize = false;
 
-        let devices: Vec<String> = if let Ok(read_guard) = self.devices_lock.read() {
+        self.profiles_ui(ui);
+
+        ui.horizontal(|ui| {
+            ui.set_enabled(!self.connected_to_device);
+            ui.label("Source");
+            ui.selectable_value(&mut self.source, SourceType::Serial, "Serial");
+            ui.selectable_value(&mut self.source, SourceType::Tcp, "TCP");
+        });
+        if self.source == SourceType::Tcp {
+            self.tcp_settings_ui(ui);
+            self.frame_settings_ui(ui);
+            return;
+        }
+
+        let devices: Vec<PortInfo> = if let Ok(read_guard) = self.devices_lock.read() {
             read_guard.clone()
         } else {
             vec![]
         };
 
-        if !devices.contains(&self.device) {
+        if !contains_device(&devices, &self.device) {
             self.device.clear();
         }
 
@@ -35,24 +288,32 @@ impl MyApp {
         let old_name = self.device.clone();
         ui.horizontal(|ui| {
             let dev_text = self.device.replace("/dev/tty.", "");
+            let dev_label = devices
+                .iter()
+                .find(|dev| dev.name == self.device)
+                .map(|dev| dev.label())
+                .unwrap_or_default();
             ui.horizontal(|ui| {
                 ui.set_enabled(!self.connected_to_device);
-                let _response = egui::ComboBox::from_id_source("Device")
+                let response = egui::ComboBox::from_id_source("Device")
                     .selected_text(dev_text)
-                    .width(RIGHT_PANEL_WIDTH * 0.92 - 155.0)
+                    .width(self.gui_conf.right_panel_width * 0.92 - 155.0)
                     .show_ui(ui, |ui| {
                         devices
                             .into_iter()
                             // on macOS each device appears as /dev/tty.* and /dev/cu.*
                             // we only display the /dev/tty.* here
-                            .filter(|dev| !dev.contains("/dev/cu."))
+                            .filter(|dev| !dev.name.contains("/dev/cu."))
                             .for_each(|dev| {
                                 // this makes the names shorter in the UI on UNIX and UNIX-like platforms
-                                let dev_text = dev.replace("/dev/tty.", "");
-                                ui.selectable_value(&mut self.device, dev, dev_text);
+                                let dev_text = dev.label().replace("/dev/tty.", "");
+                                ui.selectable_value(&mut self.device, dev.name, dev_text);
                             });
                     })
                     .response;
+                if !dev_label.is_empty() {
+                    response.on_hover_text(dev_label);
+                }
                 // let selected_new_device = response.changed();  //somehow this does not work
                 // if selected_new_device {
                 if old_name != self.device {
@@ -105,12 +366,34 @@ impl MyApp {
                     self.show_warning_window = WindowFeedback::None;
                 }
             }
+            // any baud rate can be typed in, the presets are quick-picks
+            let baud_text_id = egui::Id::new("Baud Rate Text");
+            if !ui.memory(|mem| mem.has_focus(baud_text_id)) {
+                self.baud_text = self.serial_devices.devices[self.device_idx]
+                    .baud_rate
+                    .to_string();
+            }
+            let response = ui.add(
+                TextEdit::singleline(&mut self.baud_text)
+                    .id(baud_text_id)
+                    .desired_width(55.0),
+            );
+            if response.lost_focus() {
+                match self.baud_text.trim().parse::<u32>() {
+                    Ok(baud_rate) if baud_rate > 0 => {
+                        self.serial_devices.devices[self.device_idx].baud_rate = baud_rate;
+                    }
+                    _ => {
+                        print_to_console(
+                            &self.print_lock,
+                            Print::Error(format!("Invalid baud rate: {:?}", self.baud_text)),
+                        );
+                    }
+                }
+            }
             egui::ComboBox::from_id_source("Baud Rate")
-                .selected_text(format!(
-                    "{}",
-                    self.serial_devices.devices[self.device_idx].baud_rate
-                ))
-                .width(80.0)
+                .selected_text("")
+                .width(15.0)
                 .show_ui(ui, |ui| {
                     BAUD_RATES.iter().for_each(|baud_rate| {
                         ui.selectable_value(
@@ -120,18 +403,40 @@ impl MyApp {
                         );
                     });
                 });
-            let connect_text = if self.connected_to_device {
+            // while waiting for a device to reconnect, the name is still set
+            let waiting_for_reconnect = !self.connected_to_device
+                && self
+                    .device_lock
+                    .read()
+                    .map(|d| !d.name.is_empty())
+                    .unwrap_or(false);
+            let connect_text = if self.connected_to_device || waiting_for_reconnect {
                 "Disconnect"
             } else {
                 "Connect"
             };
-            if ui.button(connect_text).clicked() {
+            let can_connect = !self.device.is_empty();
+            let response = ui
+                .add_enabled(
+                    self.connected_to_device || waiting_for_reconnect || can_connect,
+                    Button::new(connect_text),
+                )
+                .on_disabled_hover_text("Select a device first.");
+            if response.clicked() {
+                let connect = !self.connected_to_device && !waiting_for_reconnect;
+                if connect && self.gui_conf.clear_on_connect {
+                    // before the port is opened, so that none of the new data is cleared
+                    self.clear_data();
+                }
                 if let Ok(mut device) = self.device_lock.write() {
-                    if self.connected_to_device {
+                    if self.connected_to_device || waiting_for_reconnect {
                         device.name.clear();
                     } else {
-                        device.name = self.serial_devices.devices[self.device_idx].name.clone();
-                        device.baud_rate = self.serial_devices.devices[self.device_idx].baud_rate;
+                        // pass the full configuration (data bits, parity, stop bits, ..)
+                        *device = self.serial_devices.devices[self.device_idx].clone();
+                        // the lines are asserted when the port is opened
+                        self.dtr = true;
+                        self.rts = true;
                     }
                 }
             }
@@ -219,6 +524,7 @@ impl MyApp {
                         StopBits::Two.to_string(),
                     );
                 });
+            let old_flow_control = self.serial_devices.devices[self.device_idx].flow_control;
             egui::ComboBox::from_id_source("Flow Control")
                 .selected_text(
                     self.serial_devices.devices[self.device_idx]
@@ -242,37 +548,349 @@ impl MyApp {
                         FlowControl::Software,
                         FlowControl::Software.to_string(),
                     );
-                });
-            egui::ComboBox::from_id_source("Timeout")
-                .selected_text(
-                    self.serial_devices.devices[self.device_idx]
-                        .timeout
-                        .as_millis()
-                        .to_string(),
+                })
+                .response
+                .on_hover_text("None, RTS/CTS (Hardware) or XON/XOFF (Software).");
+            let flow_control = self.serial_devices.devices[self.device_idx].flow_control;
+            if self.connected_to_device && old_flow_control != flow_control {
+                // the serial thread reopens the port with the new flow control
+                if let Ok(mut device) = self.device_lock.write() {
+                    device.flow_control = flow_control;
+                }
+            }
+            let mut timeout_ms = self.serial_devices.devices[self.device_idx]
+                .timeout
+                .as_millis() as u64;
+            if ui
+                .add(
+                    egui::DragValue::new(&mut timeout_ms)
+                        .clamp_range(0..=10_000)
+                        .suffix(" ms"),
+                )
+                .on_hover_text(
+                    "Read timeout, applied when connecting. A short timeout lowers the latency, \
+                    a long one delays the data.",
+                )
+                .changed()
+            {
+                self.serial_devices.devices[self.device_idx].timeout =
+                    Duration::from_millis(timeout_ms);
+            }
+        });
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Read Buffer");
+            ui.add(
+                egui::DragValue::new(
+                    &mut self.serial_devices.devices[self.device_idx].read_buffer_size,
                 )
-                .width(55.0)
+                .clamp_range(64..=1_048_576)
+                .suffix(" bytes"),
+            )
+            .on_hover_text(
+                "Size of the read buffer, applied when connecting. A larger buffer takes fewer \
+                reads at high data rates, a smaller one uses less memory.",
+            );
+        });
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Framing");
+            let old_framing = self.serial_devices.devices[self.device_idx].framing;
+            let framing = &mut self.serial_devices.devices[self.device_idx].framing;
+            egui::ComboBox::from_id_source("Framing")
+                .selected_text(match framing {
+                    Framing::Delimiter(_) => "Byte".to_string(),
+                    Framing::FixedLength(_) => "Fixed".to_string(),
+                    framing => framing.to_string(),
+                })
+                .width(110.0)
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(
-                        &mut self.serial_devices.devices[self.device_idx].timeout,
-                        Duration::from_millis(0),
-                        "0",
-                    );
-                    ui.selectable_value(
-                        &mut self.serial_devices.devices[self.device_idx].timeout,
-                        Duration::from_millis(10),
-                        "10",
+                    for option in [
+                        Framing::Newline,
+                        Framing::CarriageReturn,
+                        Framing::Delimiter(0),
+                        Framing::FixedLength(16),
+                    ] {
+                        let selected =
+                            std::mem::discriminant(framing) == std::mem::discriminant(&option);
+                        if ui.selectable_label(selected, option.to_string()).clicked() && !selected
+                        {
+                            *framing = option;
+                        }
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "How the received bytes are split into lines, e.g. a 0x00 byte for \
+                    null-terminated messages.",
+                );
+            match framing {
+                Framing::Delimiter(byte) => {
+                    ui.add(
+                        egui::DragValue::new(byte)
+                            .hexadecimal(2, false, true)
+                            .prefix("0x"),
                     );
-                    ui.selectable_value(
-                        &mut self.serial_devices.devices[self.device_idx].timeout,
-                        Duration::from_millis(100),
-                        "100",
+                }
+                Framing::FixedLength(len) => {
+                    ui.add(
+                        egui::DragValue::new(len)
+                            .clamp_range(1..=65_536)
+                            .suffix(" bytes"),
                     );
-                    ui.selectable_value(
-                        &mut self.serial_devices.devices[self.device_idx].timeout,
-                        Duration::from_millis(1000),
-                        "1000",
+                }
+                _ => {}
+            }
+            let framing = *framing;
+            if old_framing != framing {
+                // the serial thread splits the following data with the new framing
+                if let Ok(mut device) = self.device_lock.write() {
+                    device.framing = framing;
+                }
+            }
+        });
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(
+                    &mut self.serial_devices.devices[self.device_idx].auto_reconnect,
+                    "Auto-reconnect",
+                )
+                .on_hover_text("Reopen the port when the device is unplugged and plugged back in.")
+                .changed()
+            {
+                if let Ok(mut device) = self.device_lock.write() {
+                    device.auto_reconnect =
+                        self.serial_devices.devices[self.device_idx].auto_reconnect;
+                }
+            }
+            ui.checkbox(&mut self.gui_conf.clear_on_connect, "Clear data on connect")
+                .on_hover_text(
+                    "Start with an empty plot on every connect, otherwise the new data is \
+                    appended to the existing data.",
+                );
+        });
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let mut changed = ui
+                .checkbox(&mut self.gui_conf.watchdog, "No data timeout")
+                .on_hover_text(
+                    "Report an error and show a yellow indicator when the connected device \
+                    sends nothing for this time.",
+                )
+                .changed();
+            changed |= ui
+                .add_enabled(
+                    self.gui_conf.watchdog,
+                    egui::DragValue::new(&mut self.gui_conf.watchdog_seconds)
+                        .clamp_range(1.0..=86_400.0)
+                        .speed(0.5)
+                        .suffix(" s"),
+                )
+                .changed();
+            if changed {
+                self.gui_event_tx
+                    .send(GuiEvent::SetWatchdog(watchdog_timeout(&self.gui_conf)))
+                    .expect("Failed to send watchdog");
+            }
+        });
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.set_enabled(self.connected_to_device);
+            let dtr_text = format!("DTR: {}", if self.dtr { "On" } else { "Off" });
+            if ui
+                .selectable_label(self.dtr, dtr_text)
+                .on_hover_text("Set the Data Terminal Ready line.")
+                .clicked()
+            {
+                self.dtr = !self.dtr;
+                self.send_port_control(PortControl::SetDtr(self.dtr));
+            }
+            let rts_text = format!("RTS: {}", if self.rts { "On" } else { "Off" });
+            if ui
+                .selectable_label(self.rts, rts_text)
+                .on_hover_text("Set the Request To Send line.")
+                .clicked()
+            {
+                self.rts = !self.rts;
+                self.send_port_control(PortControl::SetRts(self.rts));
+            }
+        });
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.set_enabled(self.connected_to_device);
+            if ui
+                .button("Send Break")
+                .on_hover_text("Hold the TX line in the break state.")
+                .clicked()
+            {
+                self.send_port_control(PortControl::Break(Duration::from_millis(
+                    self.gui_conf.break_duration_ms,
+                )));
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.gui_conf.break_duration_ms)
+                    .clamp_range(1..=5000)
+                    .suffix(" ms"),
+            );
+        });
+        self.frame_settings_ui(ui);
+    }
+
+    /// Layout of binary frames (sync bytes, field types and byte order), decoded instead of lines.
+    fn frame_settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(5.0);
+        let layout = &mut self.serial_devices.devices[self.device_idx].frame_layout;
+        let old_layout = layout.clone();
+        egui::CollapsingHeader::new("Binary Frames")
+            .default_open(layout.enable)
+            .show(ui, |ui| {
+                ui.checkbox(&mut layout.enable, "Decode binary frames")
+                    .on_hover_text(
+                        "Decode fixed-size frames into the columns instead of reading text lines, \
+                        bytes before the sync bytes are skipped.",
                     );
+                ui.horizontal(|ui| {
+                    ui.label("Sync: ");
+                    let mut remove = None;
+                    for (i, byte) in layout.sync.iter_mut().enumerate() {
+                        ui.add(
+                            egui::DragValue::new(byte)
+                                .hexadecimal(2, false, true)
+                                .prefix("0x"),
+                        )
+                        .context_menu(|ui| {
+                            if ui.button("Remove").clicked() {
+                                remove = Some(i);
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                    if let Some(i) = remove {
+                        layout.sync.remove(i);
+                    }
+                    if ui.small_button("+").clicked() {
+                        layout.sync.push(0);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Byte order: ");
+                    ui.selectable_value(&mut layout.little_endian, true, "Little endian");
+                    ui.selectable_value(&mut layout.little_endian, false, "Big endian");
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Fields: ");
+                    let mut remove = None;
+                    for (i, field) in layout.fields.iter_mut().enumerate() {
+                        egui::ComboBox::from_id_source(format!("Frame Field {i}"))
+                            .selected_text(field.to_string())
+                            .width(40.0)
+                            .show_ui(ui, |ui| {
+                                for field_type in FieldType::ALL {
+                                    ui.selectable_value(field, field_type, field_type.to_string());
+                                }
+                                if ui.button("Remove").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                    }
+                    if let Some(i) = remove {
+                        layout.fields.remove(i);
+                    }
+                    if ui.small_button("+").clicked() {
+                        layout.fields.push(FieldType::F32);
+                    }
                 });
+                ui.label(format!("Frame length: {} bytes", layout.frame_len()));
+            });
+        if *layout != old_layout {
+            // the serial thread picks up the new layout while connected
+            let layout = layout.clone();
+            if let Ok(mut device) = self.device_lock.write() {
+                device.frame_layout = layout;
+            }
+        }
+    }
+
+    fn clear_data(&mut self) {
+        print_to_console(
+            &self.print_lock,
+            Print::Ok("Cleared recorded Data".to_string()),
+        );
+        if let Err(err) = self.gui_event_tx.send(GuiEvent::Clear) {
+            print_to_console(
+                &self.print_lock,
+                Print::Error(format!("clear_tx thread send failed: {:?}", err)),
+            );
+        }
+        // need to clear the data here in order to prevent errors in the gui (plot)
+        self.data = DataContainer::default();
+        if let Ok(mut byte_log) = self.byte_log_lock.write() {
+            *byte_log = ByteLog::default();
+        }
+        self.hex_dump_frozen = None;
+        self.gui_event_tx
+            .send(GuiEvent::SetNames(
+                self.gui_conf.plot_options.labels.clone(),
+            ))
+            .expect("Failed to send names");
+    }
+
+    fn send_port_control(&self, control: PortControl) {
+        if let Err(err) = self.control_tx.send(control) {
+            print_to_console(
+                &self.print_lock,
+                Print::Error(format!("control_tx thread send failed: {:?}", err)),
+            );
+        }
+    }
+
+    fn tcp_settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(5.0);
+        ui.label("Host:Port");
+        ui.horizontal(|ui| {
+            ui.add_enabled(
+                !self.connected_to_device,
+                egui::TextEdit::singleline(&mut self.gui_conf.tcp_address)
+                    .desired_width(self.gui_conf.right_panel_width * 0.92 - 90.0)
+                    .hint_text("192.168.4.1:23"),
+            );
+            // the name stays set while the socket is connecting
+            let connecting = self
+                .device_lock
+                .read()
+                .map(|d| !d.name.is_empty())
+                .unwrap_or(false);
+            let connect_text = if self.connected_to_device || connecting {
+                "Disconnect"
+            } else {
+                "Connect"
+            };
+            if ui.button(connect_text).clicked() {
+                let address = self.gui_conf.tcp_address.trim().to_string();
+                let connect = !self.connected_to_device && !connecting && !address.is_empty();
+                if connect && self.gui_conf.clear_on_connect {
+                    self.clear_data();
+                }
+                if let Ok(mut device) = self.device_lock.write() {
+                    if self.connected_to_device || connecting {
+                        device.name.clear();
+                    } else if address.is_empty() {
+                        print_to_console(
+                            &self.print_lock,
+                            Print::Error("Enter an address like host:port".to_string()),
+                        );
+                    } else {
+                        device.name = format!("{TCP_PREFIX}{address}");
+                        device.line_ending =
+                            self.serial_devices.devices[self.device_idx].line_ending;
+                        device.framing = self.serial_devices.devices[self.device_idx].framing;
+                        device.frame_layout = self.serial_devices.devices[self.device_idx]
+                            .frame_layout
+                            .clone();
+                    }
+                }
+            }
         });
     }
 
@@ -288,27 +906,70 @@ impl MyApp {
                         "{} Save CSV",
                         egui_phosphor::regular::FLOPPY_DISK
                     )))
-                    .on_hover_text("Save Plot Data to CSV.")
+                    .on_hover_text("Save Plot Data to CSV, JSON or NDJSON.")
                     .clicked()
                     || ui.input_mut(|i| i.consume_shortcut(&SAVE_FILE_SHORTCUT))
                 {
                     let cwd = std::env::current_dir().unwrap_or_default();
-                    if let Some(path) = rfd::FileDialog::new()
+                    let format = self.gui_conf.save_format;
+                    let visible_range = self.save_visible_range.then(|| self.visible_time_range());
+                    if matches!(visible_range, Some(VisibleRange::Empty)) {
+                        print_to_console(
+                            &self.print_lock,
+                            Print::Error("no samples are visible, nothing to save".to_string()),
+                        );
+                    } else if let Some(path) = rfd::FileDialog::new()
                         .set_directory(cwd)
-                        .set_file_name("data.csv")
+                        .add_filter(format.to_string(), &[format.extension()])
+                        .set_file_name(format!("data.{}", format.extension()))
                         .save_file()
                     {
                         self.picked_path = path;
-                        self.picked_path.set_extension("csv");
-                        if let Err(e) = self.gui_event_tx.send(GuiEvent::SaveCSV(FileOptions {
+                        self.picked_path.set_extension(format.extension());
+                        let time_range = match visible_range {
+                            Some(VisibleRange::Time(start, end)) => Some((start, end)),
+                            Some(_) => {
+                                print_to_console(
+                                    &self.print_lock,
+                                    Print::Error(
+                                        "the visible range can only be saved with a time or \
+                                        point x-axis, saving all data"
+                                            .to_string(),
+                                    ),
+                                );
+                                None
+                            }
+                            None => None,
+                        };
+                        let port = match self.device_lock.read() {
+                            Ok(device) if device.name.starts_with(TCP_PREFIX) => {
+                                device.name.clone()
+                            }
+                            Ok(device) if !device.name.is_empty() => {
+                                format!("{} @ {} baud", device.name, device.baud_rate)
+                            }
+                            _ => "".to_string(),
+                        };
+                        let file_options = FileOptions {
                             file_path: self.picked_path.clone(),
+                            format,
                             save_absolute_time: self.gui_conf.save_absolute_time,
                             save_raw_traffic: self.save_raw,
-                        })) {
-                            print_to_console(
-                                &self.print_lock,
-                                Print::Error(format!("save_tx thread send failed: {:?}", e)),
-                            );
+                            append: self.gui_conf.append_csv && time_range.is_none(),
+                            time_range,
+                            precision: self.gui_conf.save_precision,
+                            include_metadata: self.gui_conf.save_metadata,
+                            port,
+                            include_index: self.gui_conf.save_index,
+                            overwrite: self.gui_conf.overwrite_policy,
+                        };
+                        // an unchanged target path means that the file is new or overwritten
+                        if file_options.overwrite == OverwritePolicy::Prompt
+                            && file_options.target_path() != file_options.file_path
+                        {
+                            self.pending_save = Some(file_options);
+                        } else {
+                            self.send_save(file_options);
                         }
                     }
                 };
@@ -318,13 +979,31 @@ impl MyApp {
                         "{} Save Plot",
                         egui_phosphor::regular::FLOPPY_DISK
                     )))
-                    .on_hover_text("Save an image of the Plot.")
+                    .on_hover_text("Export the Plot as PNG image.")
                     .clicked()
                     || ui.input_mut(|i| i.consume_shortcut(&SAVE_PLOT_SHORTCUT))
                 {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
                 }
                 ui.end_row();
+                if ui
+                    .button(egui::RichText::new(format!(
+                        "{} Load CSV",
+                        egui_phosphor::regular::FOLDER_OPEN
+                    )))
+                    .on_hover_text("Load a previously saved CSV into the Plot.")
+                    .clicked()
+                {
+                    let cwd = std::env::current_dir().unwrap_or_default();
+                    if let Some(path) = rfd::FileDialog::new()
+                        .set_directory(cwd)
+                        .add_filter("CSV", &["csv"])
+                        .pick_file()
+                    {
+                        self.load_csv(&path);
+                    }
+                }
+                ui.end_row();
                 if ui
                     .button(egui::RichText::new(format!(
                         "{} Clear Data",
@@ -334,40 +1013,215 @@ impl MyApp {
                     .clicked()
                     || ui.input_mut(|i| i.consume_shortcut(&CLEAR_PLOT_SHORTCUT))
                 {
+                    self.clear_data();
+                }
+                let pause_text = if self.plot_paused {
+                    format!("{} Resume Plot", egui_phosphor::regular::PLAY)
+                } else {
+                    format!("{} Pause Plot", egui_phosphor::regular::PAUSE)
+                };
+                if ui
+                    .button(egui::RichText::new(pause_text))
+                    .on_hover_text("Freeze the plot view, data is still being captured.")
+                    .clicked()
+                {
+                    self.plot_paused = !self.plot_paused;
+                    self.plot_resumed = !self.plot_paused;
+                }
+                ui.end_row();
+                if ui
+                    .button(egui::RichText::new(format!(
+                        "{} Capture Reference",
+                        egui_phosphor::regular::CAMERA
+                    )))
+                    .on_hover_text("Keep the current data to compare it with the live data.")
+                    .clicked()
+                {
+                    self.reference = Some(self.data.clone());
                     print_to_console(
                         &self.print_lock,
-                        Print::Ok("Cleared recorded Data".to_string()),
+                        Print::Ok(format!(
+                            "captured {} samples as reference",
+                            self.data.time.len()
+                        )),
                     );
-                    if let Err(err) = self.gui_event_tx.send(GuiEvent::Clear) {
-                        print_to_console(
-                            &self.print_lock,
-                            Print::Error(format!("clear_tx thread send failed: {:?}", err)),
-                        );
-                    }
-                    // need to clear the data here in order to prevent errors in the gui (plot)
-                    self.data = DataContainer::default();
-                    self.gui_event_tx
-                        .send(GuiEvent::SetNames(
-                            self.gui_conf.plot_options.labels.clone(),
-                        ))
-                        .expect("Failed to send names");
+                }
+                if ui
+                    .add_enabled(
+                        self.reference.is_some(),
+                        Button::new(format!("{} Clear Reference", egui_phosphor::regular::X)),
+                    )
+                    .clicked()
+                {
+                    self.reference = None;
                 }
                 ui.end_row();
-                ui.label("Save Raw Traffic");
-                ui.add(toggle(&mut self.save_raw))
-                    .on_hover_text("Save second CSV containing raw traffic.")
-                    .changed();
-                ui.end_row();
-                ui.label("Save Absolute Time");
-                ui.add(toggle(&mut self.gui_conf.save_absolute_time))
-                    .on_hover_text("Save absolute time in CSV.");
+                ui.label("Save Format");
+                egui::ComboBox::from_id_source("Save Format")
+                    .selected_text(self.gui_conf.save_format.to_string())
+                    .width(80.0)
+                    .show_ui(ui, |ui| {
+                        for format in [FileFormat::Csv, FileFormat::Json, FileFormat::NdJson] {
+                            ui.selectable_value(
+                                &mut self.gui_conf.save_format,
+                                format,
+                                format.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "NDJSON writes one {\"t\": .., \"values\": [..]} object per line.",
+                    );
                 ui.end_row();
-            });
-        ui.add_space(25.0);
-        global_dark_light_mode_buttons(ui);
-        ui.add_space(25.0);
-        self.gui_conf.dark_mode = ui.visuals() == &Visuals::dark();
-        ui.horizontal(|ui| {
+                ui.label("Decimal Places");
+                ui.horizontal(|ui| {
+                    let mut limited = self.gui_conf.save_precision.is_some();
+                    if ui
+                        .checkbox(&mut limited, "")
+                        .on_hover_text("Round the values in a CSV file, otherwise full precision.")
+                        .changed()
+                    {
+                        self.gui_conf.save_precision = limited.then_some(3);
+                    }
+                    if let Some(precision) = &mut self.gui_conf.save_precision {
+                        ui.add(egui::DragValue::new(precision).clamp_range(0..=15))
+                            .on_hover_text("0 writes integers.");
+                    } else {
+                        ui.label("Full");
+                    }
+                });
+                ui.end_row();
+                ui.label("If File Exists");
+                egui::ComboBox::from_id_source("Overwrite Policy")
+                    .selected_text(self.gui_conf.overwrite_policy.to_string())
+                    .width(80.0)
+                    .show_ui(ui, |ui| {
+                        for policy in [
+                            OverwritePolicy::Prompt,
+                            OverwritePolicy::RenameIfExists,
+                            OverwritePolicy::Overwrite,
+                        ] {
+                            ui.selectable_value(
+                                &mut self.gui_conf.overwrite_policy,
+                                policy,
+                                policy.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Rename saves to a new file with a numbered suffix.");
+                ui.end_row();
+                ui.label("Save Metadata");
+                ui.add(toggle(&mut self.gui_conf.save_metadata)).on_hover_text(
+                    "Write the port, capture start and sample count as # comment lines before the CSV header.",
+                );
+                ui.end_row();
+                ui.label("Save Index");
+                ui.add(toggle(&mut self.gui_conf.save_index))
+                    .on_hover_text("Write a leading index column with the number of each sample.");
+                ui.end_row();
+                ui.label("Visible Range Only");
+                ui.add(toggle(&mut self.save_visible_range))
+                    .on_hover_text("Save only the samples shown in the plot (not when appending).");
+                ui.end_row();
+                ui.label("Save Raw Traffic");
+                ui.add(toggle(&mut self.save_raw))
+                    .on_hover_text("Save second CSV containing raw traffic.")
+                    .changed();
+                ui.end_row();
+                ui.label("Save Absolute Time");
+                if ui
+                    .add(toggle(&mut self.gui_conf.save_absolute_time))
+                    .on_hover_text("Save absolute time in CSV.")
+                    .changed()
+                {
+                    self.gui_event_tx
+                        .send(GuiEvent::SetSaveAbsoluteTime(
+                            self.gui_conf.save_absolute_time,
+                        ))
+                        .expect("Failed to send save absolute time");
+                }
+                ui.end_row();
+                ui.label("Append to CSV");
+                ui.add(toggle(&mut self.gui_conf.append_csv)).on_hover_text(
+                    "Only append the rows added since the last save, the raw traffic is not saved.",
+                );
+                ui.end_row();
+                ui.label("Auto-save every [s]");
+                let mut auto_save_changed = ui
+                    .add(
+                        egui::DragValue::new(&mut self.gui_conf.auto_save_options.interval)
+                            .update_while_editing(false),
+                    )
+                    .on_hover_text("Periodically save a timestamped CSV, 0 disables it.")
+                    .changed();
+                ui.end_row();
+                ui.label("Auto-save folder");
+                let folder_text = if self
+                    .gui_conf
+                    .auto_save_options
+                    .directory
+                    .as_os_str()
+                    .is_empty()
+                {
+                    "Current".to_string()
+                } else {
+                    self.gui_conf
+                        .auto_save_options
+                        .directory
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                if ui
+                    .button(egui::RichText::new(format!(
+                        "{} {}",
+                        egui_phosphor::regular::FOLDER_OPEN,
+                        folder_text
+                    )))
+                    .on_hover_text(format!(
+                        "Folder of the auto-saved files: {:?}",
+                        self.gui_conf.auto_save_options.directory
+                    ))
+                    .clicked()
+                {
+                    let cwd = std::env::current_dir().unwrap_or_default();
+                    if let Some(path) = rfd::FileDialog::new().set_directory(cwd).pick_folder() {
+                        self.gui_conf.auto_save_options.directory = path;
+                        auto_save_changed = true;
+                    }
+                }
+                if auto_save_changed {
+                    self.gui_event_tx
+                        .send(GuiEvent::SetAutoSave(
+                            self.gui_conf.auto_save_options.clone(),
+                        ))
+                        .expect("Failed to send auto-save options");
+                }
+                ui.end_row();
+            });
+        ui.add_space(25.0);
+        ui.horizontal(|ui| {
+            ui.label("Theme: ");
+            for theme in ThemePreference::ALL {
+                ui.selectable_value(&mut self.gui_conf.theme, theme, theme.to_string());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Zoom: ");
+            let mut zoom_factor = ctx.zoom_factor();
+            if ui
+                .add(egui::Slider::new(&mut zoom_factor, 0.5..=3.0).step_by(0.1))
+                .on_hover_text("Scales the whole UI, also with ctrl + and ctrl -.")
+                .changed()
+            {
+                ctx.set_zoom_factor(zoom_factor);
+            }
+        });
+        ui.add_space(25.0);
+        ui.horizontal(|ui| {
             if ui.button("Clear Device History").clicked() {
                 self.serial_devices = SerialDevices::default();
                 self.device.clear();
@@ -377,33 +1231,275 @@ impl MyApp {
         });
     }
 
+    pub fn statistics_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Statistics")
+            .default_open(false)
+            .show(ui, |ui| {
+                if self.stats_updated.elapsed() >= STATS_UPDATE_INTERVAL
+                    || self.stats.len() != self.data.dataset.len()
+                {
+                    self.stats = self
+                        .data
+                        .dataset
+                        .iter()
+                        .map(SeriesStats::from_values)
+                        .collect();
+                    self.stats_updated = Instant::now();
+                }
+                egui::Grid::new("statistics")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        ui.label("Min");
+                        ui.label("Max");
+                        ui.label("Mean");
+                        ui.label("Std Dev");
+                        ui.end_row();
+                        for (i, stats) in self.stats.iter().enumerate() {
+                            let visible = self
+                                .gui_conf
+                                .plot_options
+                                .visible
+                                .get(i)
+                                .copied()
+                                .unwrap_or(true);
+                            if !visible {
+                                continue;
+                            }
+                            let name = self
+                                .gui_conf
+                                .plot_options
+                                .labels
+                                .get(i)
+                                .cloned()
+                                .unwrap_or_else(|| format!("Column {i}"));
+                            ui.label(name);
+                            match stats {
+                                Some(stats) => {
+                                    for value in [stats.min, stats.max, stats.mean, stats.std_dev] {
+                                        ui.monospace(format!("{value:.4}"));
+                                    }
+                                }
+                                None => {
+                                    for _ in 0..4 {
+                                        ui.monospace("-");
+                                    }
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Loads a saved profile or saves the current settings under a name.
+    fn profiles_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            // the port settings are applied when connecting
+            ui.set_enabled(!self.connected_to_device);
+            ui.label("Profile");
+            let mut loaded = None;
+            egui::ComboBox::from_id_source("Profile")
+                .selected_text("")
+                .width(15.0)
+                .show_ui(ui, |ui| {
+                    for profile in self.profiles.profiles.iter() {
+                        if ui.selectable_label(false, &profile.name).clicked() {
+                            loaded = Some(profile.clone());
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Load the port, parsing and plot settings of a profile.");
+            ui.add(
+                TextEdit::singleline(&mut self.profile_name)
+                    .desired_width(self.gui_conf.right_panel_width * 0.92 - 200.0)
+                    .hint_text("profile name"),
+            );
+            let name = self.profile_name.trim().to_string();
+            let exists = self.profiles.profiles.iter().any(|p| p.name == name);
+            if ui
+                .add_enabled(!name.is_empty(), Button::new("Save"))
+                .on_hover_text(if exists {
+                    "Replace the profile with the current settings."
+                } else {
+                    "Save the current settings as a new profile."
+                })
+                .clicked()
+            {
+                let profile = Profile {
+                    name: name.clone(),
+                    device: self.serial_devices.devices[self.device_idx].clone(),
+                    parsing_options: self.gui_conf.parsing_options.clone(),
+                    plot_options: self.gui_conf.plot_options.clone(),
+                };
+                match self.profiles.profiles.iter_mut().find(|p| p.name == name) {
+                    Some(existing) => *existing = profile,
+                    None => self.profiles.profiles.push(profile),
+                }
+                save_profiles(&self.profiles);
+                print_to_console(&self.print_lock, Print::Ok(format!("saved profile {name}")));
+            }
+            if ui
+                .add_enabled(exists, Button::new(egui_phosphor::regular::TRASH))
+                .on_hover_text("Delete the profile.")
+                .clicked()
+            {
+                self.profiles.profiles.retain(|p| p.name != name);
+                save_profiles(&self.profiles);
+            }
+            if let Some(profile) = loaded {
+                self.load_profile(profile);
+            }
+        });
+    }
+
+    fn load_profile(&mut self, profile: Profile) {
+        let device = &mut self.serial_devices.devices[self.device_idx];
+        *device = Device {
+            name: device.name.clone(),
+            ..profile.device
+        };
+        save_serial_settings(&self.serial_devices);
+        self.gui_conf.parsing_options = profile.parsing_options;
+        self.gui_conf.plot_options = profile.plot_options;
+        if let Err(e) = self.gui_conf.parsing_options.compile_regex() {
+            print_to_console(
+                &self.print_lock,
+                Print::Error(format!("invalid regex: {e}")),
+            );
+        }
+        if let Err(e) = self.gui_conf.parsing_options.compile_computed() {
+            print_to_console(
+                &self.print_lock,
+                Print::Error(format!("invalid expression: {e}")),
+            );
+        }
+        send_gui_settings(&self.gui_event_tx, &self.gui_conf);
+        // the names are taken over if the profile fits the received columns
+        if self.gui_conf.plot_options.labels.len() == self.data.names.len() {
+            self.gui_event_tx
+                .send(GuiEvent::SetNames(
+                    self.gui_conf.plot_options.labels.clone(),
+                ))
+                .expect("Failed to send names");
+        }
+        print_to_console(
+            &self.print_lock,
+            Print::Ok(format!("loaded profile {}", profile.name)),
+        );
+        self.profile_name = profile.name;
+    }
+
+    pub fn send_save(&self, file_options: FileOptions) {
+        if let Err(e) = self.gui_event_tx.send(GuiEvent::SaveCSV(file_options)) {
+            print_to_console(
+                &self.print_lock,
+                Print::Error(format!("save_tx thread send failed: {:?}", e)),
+            );
+        }
+    }
+
+    pub fn load_csv(&mut self, path: &Path) {
+        match load_from_csv(path) {
+            Ok(data) => {
+                print_to_console(
+                    &self.print_lock,
+                    Print::Ok(format!("loaded data file {:?}", path)),
+                );
+                self.gui_conf.plot_options.labels = data.names.clone();
+                self.data = data.clone();
+                if let Err(e) = self.gui_event_tx.send(GuiEvent::LoadData(data)) {
+                    print_to_console(
+                        &self.print_lock,
+                        Print::Error(format!("load_tx thread send failed: {:?}", e)),
+                    );
+                }
+            }
+            Err(e) => {
+                print_to_console(
+                    &self.print_lock,
+                    Print::Error(format!("failed to load file {:?}: {:?}", path, e)),
+                );
+            }
+        }
+    }
+
     pub fn debug_console_ui(&mut self, ui: &mut egui::Ui) {
         if let Ok(read_guard) = self.print_lock.read() {
             self.console = read_guard.clone();
         }
-        let num_rows = self.console.len();
+        let lines: Vec<(String, egui::Color32)> = self
+            .console
+            .iter()
+            .flat_map(|row| row.scroll_area_message(&self.gui_conf))
+            .map(|msg| (msg.label + msg.content.as_str(), msg.color))
+            .filter(|(line, _)| self.console_filter.matches(line))
+            .collect();
+        self.console_filter.match_count = lines.len();
+        let num_rows = lines.len();
         let row_height = ui.text_style_height(&egui::TextStyle::Body);
-        ui.label("Debug Info:");
-        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Debug Info:");
+            if ui
+                .button(egui_phosphor::regular::COPY)
+                .on_hover_text("Copy all shown lines to the clipboard.")
+                .clicked()
+            {
+                let text = lines
+                    .iter()
+                    .map(|(line, _)| line.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.output_mut(|o| o.copied_text = text);
+            }
+            ui.label("Scrollback:");
+            if ui
+                .add(
+                    egui::DragValue::new(&mut self.gui_conf.console_scrollback)
+                        .clamp_range(100..=1_000_000)
+                        .suffix(" lines"),
+                )
+                .on_hover_text("Number of lines kept, the oldest ones are dropped first.")
+                .changed()
+            {
+                set_console_scrollback(&self.print_lock, self.gui_conf.console_scrollback);
+            }
+        });
+        ui.add_space(5.0);
+        filter_ui(ui, &mut self.console_filter, 150.0);
+        ui.add_space(5.0);
         egui::ScrollArea::vertical()
             .id_source("console_scroll_area")
             .auto_shrink([false; 2])
             .stick_to_bottom(true)
             .max_height(ui.available_height())
             .show_rows(ui, row_height, num_rows, |ui, _row_range| {
-                let content: String = self
-                    .console
+                let content: String = lines
                     .iter()
-                    .flat_map(|row| row.scroll_area_message(&self.gui_conf))
-                    .map(|msg| msg.label + msg.content.as_str())
+                    .map(|(line, _)| line.as_str())
                     .collect::<Vec<_>>()
                     .join("\n");
+                // every line is colored according to its type
+                let mut layouter = |ui: &egui::Ui, _text: &str, wrap_width: f32| {
+                    let mut job = egui::text::LayoutJob::default();
+                    for (i, (line, color)) in lines.iter().enumerate() {
+                        if i != 0 {
+                            job.append("\n", 0.0, TextFormat::simple(DEFAULT_FONT_ID, *color));
+                        }
+                        job.append(line, 0.0, TextFormat::simple(DEFAULT_FONT_ID, *color));
+                    }
+                    job.wrap.max_width = wrap_width;
+                    ui.fonts(|fonts| fonts.layout_job(job))
+                };
                 // we need to add it as one multiline object, such that we can select and copy
                 // text over multiple lines
                 ui.add(
                     egui::TextEdit::multiline(&mut content.as_str())
                         .font(DEFAULT_FONT_ID) // for cursor height
-                        .lock_focus(true), // TODO: add a layouter to highlight the labels
+                        .lock_focus(true)
+                        .layouter(&mut layouter),
                 );
             });
     }
@@ -417,22 +1513,507 @@ impl MyApp {
             ui.available_height() * self.plot_serial_display_ratio
         };
         let plots_height = height;
+        let number_of_plots = self.gui_conf.plot_options.panels.len();
+        let toolbar_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y;
         // need to subtract 12.0, this seems to be the height of the separator of two adjacent plots
-        let plot_height = plots_height / (self.gui_conf.plot_options.number_of_plots as f32) - 12.0;
+        let plot_height = plots_height / (number_of_plots as f32) - 12.0 - toolbar_height;
 
-        let mut graphs: Vec<Vec<PlotPoint>> = vec![vec![]; self.data.dataset.len()];
-        let window = self.data.dataset[0]
-            .len()
-            .saturating_sub(self.gui_conf.plot_options.plotting_range);
+        let alarm_flash_on = ui.input(|i| i.time).fract() < 0.5;
+
+        // let t_fmt = |x, _n, _range: &RangeInclusive<f64>| format!("{:4.2} s", x);
 
-        for (i, time) in self.data.time[window..].iter().enumerate() {
+        ui.vertical_centered_justified(|ui| {
+            self.plot_location = None;
+            self.plot_x_bounds = None;
+            let mut panel_edit = None;
+            for graph_idx in 0..number_of_plots {
+                if graph_idx != 0 {
+                    ui.separator();
+                }
+                if let Some(edit) = self.panel_toolbar_ui(ui, graph_idx) {
+                    panel_edit = Some((graph_idx, edit));
+                }
+
+                let panel = self.gui_conf.plot_options.panels[graph_idx].clone();
+                let window = self.plot_window(&panel);
+                let max_points = self.gui_conf.plot_options.max_plot_points;
+                let series_graphs = self.series_graphs(window);
+                let y_bounds = match self.gui_conf.plot_options.y_range {
+                    YAxisRange::Full => None,
+                    YAxisRange::Manual => self.manual_y_bounds(&panel, &series_graphs),
+                    YAxisRange::Percentile => self.percentile_y_bounds(&panel, &series_graphs),
+                };
+                let (graphs, envelopes): (Vec<Vec<PlotPoint>>, Vec<Vec<Vec<PlotPoint>>>) =
+                    series_graphs
+                        .into_iter()
+                        .map(|graph| {
+                            let reduced = if panel.envelope {
+                                envelope(&graph, max_points)
+                            } else {
+                                None
+                            };
+                            reduced.unwrap_or_else(|| (decimate(graph, max_points), vec![]))
+                        })
+                        .unzip();
+
+                let marker_xs: Vec<(f64, String)> =
+                    if self.gui_conf.plot_options.show_command_markers {
+                        self.data
+                            .markers
+                            .iter()
+                            .filter_map(|m| Some((self.marker_x(m, window)?, m.label.clone())))
+                            .collect()
+                    } else {
+                        vec![]
+                    };
+
+                let trigger_x = if self.gui_conf.plot_options.trigger.enable {
+                    self.trigger_x(window)
+                } else {
+                    None
+                };
+
+                let reference_graphs: Vec<Vec<PlotPoint>> = match &self.reference {
+                    Some(reference) if self.gui_conf.plot_options.show_reference => self
+                        .reference_graphs(reference, window)
+                        .into_iter()
+                        .map(|graph| decimate(graph, self.gui_conf.plot_options.max_plot_points))
+                        .collect(),
+                    _ => vec![],
+                };
+
+                let plot_options = &self.gui_conf.plot_options;
+                let xy_graph: Option<Vec<PlotPoint>> = match (
+                    &plot_options.plot_mode,
+                    self.data.dataset.get(plot_options.xy_x_series),
+                    self.data.dataset.get(plot_options.xy_y_series),
+                ) {
+                    (PlotMode::Xy, Some(xs), Some(ys)) => {
+                        // the buffers may differ in length, the zip stops at the shorter one
+                        Some(
+                            xs.iter()
+                                .zip(ys.iter())
+                                .skip(window)
+                                .filter_map(|(x, y)| match plot_options.y_axis {
+                                    YAxisScale::Linear => Some(PlotPoint { x: *x, y: *y }),
+                                    YAxisScale::Log10 if *y > 0.0 => Some(PlotPoint {
+                                        x: *x,
+                                        y: y.log10(),
+                                    }),
+                                    YAxisScale::Log10 => None,
+                                })
+                                .collect(),
+                        )
+                    }
+                    _ => None,
+                };
+
+                let mut signal_plot = Plot::new(format!("data-{}", panel.id))
+                    .height(plot_height)
+                    .width(width)
+                    .auto_bounds([true, true].into())
+                    .legend(Legend::default())
+                    .x_grid_spacer(log_grid_spacer(10))
+                    .y_grid_spacer(log_grid_spacer(10));
+
+                let plot_options = &self.gui_conf.plot_options;
+                let unit = |i: usize| plot_options.units.get(i).filter(|u| !u.is_empty());
+                if xy_graph.is_some() {
+                    if let Some(unit) = unit(plot_options.xy_x_series) {
+                        signal_plot = signal_plot.x_axis_label(unit.clone());
+                    }
+                    if let Some(unit) = unit(plot_options.xy_y_series) {
+                        signal_plot = signal_plot.y_axis_label(unit.clone());
+                    }
+                } else if let Some(unit) = self.panel_unit(&panel) {
+                    signal_plot = signal_plot.y_axis_label(unit);
+                }
+
+                if self.gui_conf.plot_options.x_axis == XAxisType::AbsoluteTime {
+                    signal_plot = signal_plot.x_axis_formatter(
+                        |mark: GridMark, _max_chars: usize, _range: &RangeInclusive<f64>| {
+                            format_clock_time((mark.value * 1000.0).max(0.0) as u128)
+                        },
+                    );
+                }
+
+                if self.gui_conf.plot_options.y_axis == YAxisScale::Log10 {
+                    // the plotted values are log10(y), label the axis with y itself
+                    signal_plot = signal_plot.y_axis_formatter(
+                        |mark: GridMark, _max_chars: usize, _range: &RangeInclusive<f64>| {
+                            format!("{:.3e}", 10f64.powf(mark.value))
+                        },
+                    );
+                }
+
+                // .x_axis_formatter(t_fmt);
+
+                let plot_inner = signal_plot.show(ui, |signal_plot_ui| {
+                    if self.plot_paused {
+                        // freeze the view, the data keeps coming in in the background
+                        signal_plot_ui.set_auto_bounds([false, false].into());
+                    } else if self.plot_resumed {
+                        signal_plot_ui.set_auto_bounds([true, true].into());
+                    }
+                    if let Some([min, max]) = y_bounds.filter(|_| !self.plot_paused) {
+                        // the y range follows the chosen mode, the x range the data
+                        let bounds = signal_plot_ui.plot_bounds();
+                        signal_plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                            [bounds.min()[0], min],
+                            [bounds.max()[0], max],
+                        ));
+                        signal_plot_ui.set_auto_bounds([true, false].into());
+                    }
+                    let line_width = self.gui_conf.plot_options.line_width;
+                    let show_markers = self.gui_conf.plot_options.show_markers;
+                    if let Some(xy_graph) = &xy_graph {
+                        let name = |i: usize| self.series_label(i).unwrap_or_default();
+                        let name = format!(
+                            "{} vs {}",
+                            name(self.gui_conf.plot_options.xy_y_series),
+                            name(self.gui_conf.plot_options.xy_x_series)
+                        );
+                        let color = auto_color(0);
+                        for segment in line_segments(xy_graph) {
+                            signal_plot_ui.line(
+                                Line::new(PlotPoints::Owned(segment.to_vec()))
+                                    .name(&name)
+                                    .color(color)
+                                    .width(line_width),
+                            );
+                            if show_markers {
+                                signal_plot_ui.points(
+                                    Points::new(PlotPoints::Owned(segment.to_vec()))
+                                        .name(&name)
+                                        .color(color)
+                                        .radius(line_width + 1.0),
+                                );
+                            }
+                        }
+                        return None;
+                    }
+                    // drawn first to stay behind the live data
+                    for (i, graph) in reference_graphs.iter().enumerate() {
+                        let visible = self
+                            .gui_conf
+                            .plot_options
+                            .visible
+                            .get(i)
+                            .copied()
+                            .unwrap_or(true)
+                            && panel.series.get(i).copied().unwrap_or(true);
+                        let Some(label) = self.series_label(i) else {
+                            continue;
+                        };
+                        if !visible {
+                            continue;
+                        }
+                        let name = format!("{label} (reference)");
+                        let color = self
+                            .gui_conf
+                            .plot_options
+                            .colors
+                            .get(i)
+                            .copied()
+                            .flatten()
+                            .unwrap_or_else(|| auto_color(i))
+                            .gamma_multiply(0.4);
+                        for segment in line_segments(graph) {
+                            signal_plot_ui.line(
+                                Line::new(PlotPoints::Owned(segment.to_vec()))
+                                    .name(&name)
+                                    .color(color)
+                                    .style(LineStyle::dashed_dense())
+                                    .width(line_width),
+                            );
+                        }
+                    }
+                    for (i, graph) in graphs.iter().enumerate() {
+                        let visible = self
+                            .gui_conf
+                            .plot_options
+                            .visible
+                            .get(i)
+                            .copied()
+                            .unwrap_or(true)
+                            && panel.series.get(i).copied().unwrap_or(true);
+                        // this check needs to be here for when we change devices (not very elegant)
+                        if let Some(name) = &self.series_label(i).filter(|_| visible) {
+                            // the gaps and markers would shift the automatic colors of the next
+                            // lines, so the color is always set explicitly
+                            let mut color = self
+                                .gui_conf
+                                .plot_options
+                                .colors
+                                .get(i)
+                                .copied()
+                                .flatten()
+                                .unwrap_or_else(|| auto_color(i));
+                            // a series outside of its alarm limits flashes red
+                            if self.data.alarms_active.get(i).copied().unwrap_or(false)
+                                && alarm_flash_on
+                            {
+                                color = egui::Color32::RED;
+                            }
+                            for band in envelopes.get(i).into_iter().flatten() {
+                                signal_plot_ui.polygon(
+                                    Polygon::new(PlotPoints::Owned(band.clone()))
+                                        .name(name)
+                                        .fill_color(color.gamma_multiply(0.3))
+                                        .stroke(egui::Stroke::NONE),
+                                );
+                            }
+                            for segment in line_segments(graph) {
+                                signal_plot_ui.line(
+                                    Line::new(PlotPoints::Owned(segment.to_vec()))
+                                        .name(name)
+                                        .id(series_id(i))
+                                        .color(color)
+                                        .width(line_width),
+                                );
+                                if show_markers {
+                                    signal_plot_ui.points(
+                                        Points::new(PlotPoints::Owned(segment.to_vec()))
+                                            .name(name)
+                                            .id(series_id(i))
+                                            .color(color)
+                                            .radius(line_width + 1.0),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    if !marker_xs.is_empty() {
+                        let top = signal_plot_ui.plot_bounds().max()[1];
+                        for (x, label) in &marker_xs {
+                            signal_plot_ui.vline(
+                                VLine::new(*x)
+                                    .color(egui::Color32::from_rgb(100, 150, 250))
+                                    .style(LineStyle::dashed_loose()),
+                            );
+                            signal_plot_ui.text(
+                                Text::new(PlotPoint::new(*x, top), label.clone())
+                                    .anchor(Align2::LEFT_TOP)
+                                    .color(egui::Color32::from_rgb(100, 150, 250)),
+                            );
+                        }
+                    }
+                    if let Some(x) = trigger_x {
+                        signal_plot_ui.vline(
+                            VLine::new(x)
+                                .color(egui::Color32::from_rgb(255, 140, 0))
+                                .width(2.0)
+                                .name("Trigger"),
+                        );
+                    }
+                    // crosshair at the sample closest to the pointer
+                    let hovered = signal_plot_ui
+                        .pointer_coordinate()
+                        .and_then(|pointer| self.hovered_sample(pointer.x, window));
+                    if let Some((idx, x)) = hovered {
+                        signal_plot_ui.vline(VLine::new(x).color(egui::Color32::GRAY));
+                        return Some(idx);
+                    }
+                    None
+                });
+
+                // the panels have their own windows, the first one defines the visible range
+                if graph_idx == 0 {
+                    let bounds = plot_inner.transform.bounds();
+                    self.plot_x_bounds = Some((bounds.min()[0], bounds.max()[0]));
+                }
+
+                // the exported image contains all plots
+                let rect = plot_inner.response.rect;
+                self.plot_location = Some(match self.plot_location {
+                    Some(location) => location.union(rect),
+                    None => rect,
+                });
+
+                // the legend entries take the clicks, so the pointer is checked directly
+                let double_click_pos = ui.input(|i| {
+                    i.pointer
+                        .button_double_clicked(egui::PointerButton::Primary)
+                        .then(|| i.pointer.interact_pos())
+                        .flatten()
+                        .filter(|pos| rect.contains(*pos))
+                });
+                if let (Some(pos), Some(id)) = (double_click_pos, plot_inner.hovered_plot_item) {
+                    if let Some(i) = (0..graphs.len()).find(|i| series_id(*i) == id) {
+                        self.start_rename(ui.ctx(), i, Some(pos));
+                    }
+                }
+
+                if let Some(idx) = plot_inner.inner {
+                    plot_inner.response.on_hover_ui_at_pointer(|ui| {
+                        self.sample_readout_ui(ui, idx);
+                    });
+                }
+            }
+            self.plot_resumed = false;
+
+            let panels = &mut self.gui_conf.plot_options.panels;
+            match panel_edit {
+                Some((idx, PanelEdit::Add)) => {
+                    let id = panels.iter().map(|p| p.id + 1).max().unwrap_or(0);
+                    let series = panels[idx].series.clone();
+                    panels.insert(
+                        idx + 1,
+                        PlotPanel {
+                            id,
+                            series,
+                            ..PlotPanel::default()
+                        },
+                    );
+                }
+                Some((idx, PanelEdit::Remove)) if panels.len() > 1 => {
+                    panels.remove(idx);
+                }
+                _ => {}
+            }
+
+            if let Some(pos) = self.renaming.as_ref().and_then(|r| r.popup_pos) {
+                let ctx = ui.ctx().clone();
+                egui::Area::new(egui::Id::new("column-rename-popup"))
+                    .order(egui::Order::Foreground)
+                    .fixed_pos(pos)
+                    .show(&ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            self.rename_edit_ui(ui);
+                        });
+                    });
+            }
+        })
+    }
+
+    /// The follow setting, the envelope and the series of a plot panel, with buttons to add a
+    /// panel below and to remove it.
+    fn panel_toolbar_ui(&mut self, ui: &mut egui::Ui, index: usize) -> Option<PanelEdit> {
+        let plot_options = &mut self.gui_conf.plot_options;
+        let removable = plot_options.panels.len() > 1;
+        let labels = &plot_options.labels;
+        let panel = &mut plot_options.panels[index];
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut panel.follow, false, "Show All")
+                .on_hover_text("Plot the whole buffer (within the plotting range).");
+            ui.selectable_value(&mut panel.follow, true, "Last")
+                .on_hover_text("Scroll with the latest data, older samples stay in the buffer.");
+            ui.add_enabled(
+                panel.follow,
+                egui::DragValue::new(&mut panel.follow_seconds)
+                    .clamp_range(0.1..=f64::MAX)
+                    .speed(0.1)
+                    .suffix(" s"),
+            );
+            ui.checkbox(&mut panel.envelope, "Envelope").on_hover_text(
+                "Draw dense series as their mean in a shaded band of the minima and maxima.",
+            );
+            ui.menu_button("Series", |ui| {
+                for (shown, label) in panel.series.iter_mut().zip(labels) {
+                    ui.checkbox(shown, label);
+                }
+            });
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .add_enabled(removable, Button::new(egui_phosphor::regular::X))
+                    .on_hover_text("Remove this plot panel.")
+                    .clicked()
+                {
+                    return Some(PanelEdit::Remove);
+                }
+                if ui
+                    .button(egui_phosphor::regular::PLUS)
+                    .on_hover_text("Add a plot panel below, with its own zoom and series.")
+                    .clicked()
+                {
+                    return Some(PanelEdit::Add);
+                }
+                None
+            })
+            .inner
+        })
+        .inner
+    }
+
+    /// The percentile y range of the series shown in a panel, recomputed at most every
+    /// `Y_RANGE_UPDATE_INTERVAL`.
+    fn percentile_y_bounds(
+        &mut self,
+        panel: &PlotPanel,
+        graphs: &[Vec<PlotPoint>],
+    ) -> Option<[f64; 2]> {
+        if let Some((updated, range)) = self.y_ranges.get(&panel.id) {
+            if updated.elapsed() < Y_RANGE_UPDATE_INTERVAL {
+                return Some(*range);
+            }
+        }
+        let values = self.visible_values(panel, graphs);
+        let range = percentile_range(values, self.gui_conf.plot_options.y_percentile)?;
+        self.y_ranges.insert(panel.id, (Instant::now(), range));
+        Some(range)
+    }
+
+    /// The manual y range of a panel, `None` if neither limit is set. A limit which is not set
+    /// follows the data.
+    fn manual_y_bounds(&self, panel: &PlotPanel, graphs: &[Vec<PlotPoint>]) -> Option<[f64; 2]> {
+        let plot_options = &self.gui_conf.plot_options;
+        let [min, max] = [0, 1].map(|i| {
+            let limit = plot_options.y_limits[i];
+            match plot_options.y_axis {
+                _ if !plot_options.y_locked[i] => None,
+                YAxisScale::Linear => Some(limit),
+                // the plotted values are log10(y)
+                YAxisScale::Log10 => (limit > 0.0).then(|| limit.log10()),
+            }
+        });
+        let [data_min, data_max] = match (min, max) {
+            (None, None) => return None,
+            (Some(_), Some(_)) => [f64::NAN; 2],
+            _ => {
+                let values = self.visible_values(panel, graphs);
+                let data_min = values.iter().copied().fold(f64::INFINITY, f64::min);
+                let data_max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let margin = (data_max - data_min) * 0.05;
+                [data_min - margin, data_max + margin]
+            }
+        };
+        let (min, max) = (min.unwrap_or(data_min), max.unwrap_or(data_max));
+        (min.is_finite() && max.is_finite()).then(|| [min.min(max), min.max(max)])
+    }
+
+    /// The finite values of the series shown in a panel.
+    fn visible_values(&self, panel: &PlotPanel, graphs: &[Vec<PlotPoint>]) -> Vec<f64> {
+        let plot_options = &self.gui_conf.plot_options;
+        graphs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                plot_options.visible.get(*i).copied().unwrap_or(true)
+                    && panel.series.get(*i).copied().unwrap_or(true)
+            })
+            .flat_map(|(_, graph)| graph.iter().map(|p| p.y))
+            .filter(|y| y.is_finite())
+            .collect()
+    }
+
+    /// The series from `window` on as points of the plot.
+    fn series_graphs(&self, window: usize) -> Vec<Vec<PlotPoint>> {
+        let mut graphs: Vec<Vec<PlotPoint>> = vec![vec![]; self.data.dataset.len()];
+        for (i, time) in self.data.time.iter().skip(window).enumerate() {
             let x = match self.gui_conf.plot_options.x_axis {
                 XAxisType::Time => *time as f64 / 1000.0,
+                // loaded files may come without absolute times, these samples are left out
+                XAxisType::AbsoluteTime => self
+                    .data
+                    .absolute_time
+                    .get(i + window)
+                    .map_or(f64::NAN, |t| *t as f64 / 1000.0),
                 XAxisType::Point => (i + 1) as f64,
                 XAxisType::FirstData => self.data.dataset[0].get(i + window).unwrap().clone(),
             };
             let initial_dataset = match self.gui_conf.plot_options.x_axis {
                 XAxisType::Time => 0,
+                XAxisType::AbsoluteTime => 0,
                 XAxisType::Point => 0,
                 XAxisType::FirstData => 1,
             };
@@ -440,47 +2021,476 @@ impl MyApp {
             for k in initial_dataset..graphs.len() {
                 let graph = &mut graphs[k];
                 let data = &self.data.dataset[k];
+                let scale = self.gui_conf.plot_options.scales.get(k).unwrap_or(&1.0);
+                let offset = self.gui_conf.plot_options.offsets.get(k).unwrap_or(&0.0);
                 if self.data.time.len() == data.len() {
-                    if let Some(y) = data.get(i + window) {
-                        graph.push(PlotPoint { x, y: *y as f64 });
+                    if let Some(y) = data.get(i + window).map(|y| y * scale + offset) {
+                        match self.gui_conf.plot_options.y_axis {
+                            YAxisScale::Linear => graph.push(PlotPoint { x, y }),
+                            // keep NaN to leave a gap in the line
+                            YAxisScale::Log10 if y.is_nan() => graph.push(PlotPoint { x, y }),
+                            // non-positive values can not be displayed on a log scale
+                            YAxisScale::Log10 if y > 0.0 => {
+                                graph.push(PlotPoint { x, y: y.log10() })
+                            }
+                            YAxisScale::Log10 => {}
+                        }
                     }
                 }
             }
         }
 
-        // let t_fmt = |x, _n, _range: &RangeInclusive<f64>| format!("{:4.2} s", x);
-
-        ui.vertical_centered_justified(|ui| {
-            for graph_idx in 0..self.gui_conf.plot_options.number_of_plots {
-                if graph_idx != 0 {
-                    ui.separator();
-                }
-
-                let signal_plot = Plot::new(format!("data-{graph_idx}"))
-                    .height(plot_height)
-                    .width(width)
-                    .auto_bounds([true, true].into())
-                    .legend(Legend::default())
-                    .x_grid_spacer(log_grid_spacer(10))
-                    .y_grid_spacer(log_grid_spacer(10));
-
-                // .x_axis_formatter(t_fmt);
-
-                let plot_inner = signal_plot.show(ui, |signal_plot_ui| {
-                    for (i, graph) in graphs.iter().enumerate() {
-                        // this check needs to be here for when we change devices (not very elegant)
-                        if i < self.gui_conf.plot_options.labels.len() {
-                            signal_plot_ui.line(
-                                Line::new(PlotPoints::Owned(graph.to_vec()))
-                                    .name(&self.gui_conf.plot_options.labels[i]),
-                            );
-                        }
-                    }
+        graphs
+    }
+
+    /// The series of the reference mapped to the x-axis of the plot. In wall-clock mode the
+    /// reference is moved to start with the shown live data.
+    fn reference_graphs(&self, reference: &DataContainer, window: usize) -> Vec<Vec<PlotPoint>> {
+        let plot_options = &self.gui_conf.plot_options;
+        let clock_shift = match (
+            reference.absolute_time.front(),
+            self.data.absolute_time.get(window),
+        ) {
+            (Some(start), Some(live_start)) => *live_start as f64 - *start as f64,
+            _ => 0.0,
+        };
+        let initial_dataset = match plot_options.x_axis {
+            XAxisType::FirstData => 1,
+            _ => 0,
+        };
+        reference
+            .dataset
+            .iter()
+            .enumerate()
+            .map(|(k, data)| {
+                if k < initial_dataset || data.len() != reference.time.len() {
+                    return vec![];
+                }
+                let scale = plot_options.scales.get(k).unwrap_or(&1.0);
+                let offset = plot_options.offsets.get(k).unwrap_or(&0.0);
+                data.iter()
+                    .enumerate()
+                    .filter_map(|(i, y)| {
+                        let x = match plot_options.x_axis {
+                            XAxisType::Time => reference.time[i] as f64 / 1000.0,
+                            XAxisType::AbsoluteTime => {
+                                (*reference.absolute_time.get(i)? as f64 + clock_shift) / 1000.0
+                            }
+                            XAxisType::Point => (i + 1) as f64,
+                            XAxisType::FirstData => reference.dataset[0][i],
+                        };
+                        let y = y * scale + offset;
+                        match plot_options.y_axis {
+                            YAxisScale::Linear => Some(PlotPoint { x, y }),
+                            YAxisScale::Log10 if y.is_nan() => Some(PlotPoint { x, y }),
+                            YAxisScale::Log10 if y > 0.0 => Some(PlotPoint { x, y: y.log10() }),
+                            YAxisScale::Log10 => None,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Editor of the columns calculated from the received ones.
+    fn computed_columns_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        egui::CollapsingHeader::new("Computed Columns").show(ui, |ui| {
+            let columns = &mut self.gui_conf.parsing_options.computed;
+            let mut remove = None;
+            for (i, column) in columns.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let name = ui.add(TextEdit::singleline(&mut column.name).desired_width(70.0));
+                    ui.label("=");
+                    let expression = ui
+                        .add(
+                            TextEdit::singleline(&mut column.expression)
+                                .code_editor()
+                                .desired_width(110.0)
+                                .hint_text("col0 - col1"),
+                        )
+                        .on_hover_text(
+                            "The columns are col0, col1, .. or their names, functions like \
+                            sqrt(x) and abs(x) can be used.",
+                        );
+                    if column.expr.is_none() {
+                        ui.colored_label(egui::Color32::RED, "invalid");
+                    }
+                    if ui.small_button(egui_phosphor::regular::X).clicked() {
+                        remove = Some(i);
+                    }
+                    changed |= name.lost_focus() || expression.lost_focus();
+                });
+            }
+            if let Some(i) = remove {
+                columns.remove(i);
+                changed = true;
+            }
+            if ui
+                .button(format!("{} Add", egui_phosphor::regular::PLUS))
+                .on_hover_text("Add a column calculated from the received ones.")
+                .clicked()
+            {
+                columns.push(ComputedColumn::default());
+                changed = true;
+            }
+        });
+        if changed {
+            if let Err(e) = self.gui_conf.parsing_options.compile_computed() {
+                print_to_console(
+                    &self.print_lock,
+                    Print::Error(format!("invalid expression: {e}")),
+                );
+            }
+            self.gui_event_tx
+                .send(GuiEvent::SetComputed(
+                    self.gui_conf.parsing_options.computed.clone(),
+                ))
+                .expect("Failed to send computed columns");
+        }
+    }
+
+    /// Starts editing the name of a column in place, `popup_pos` is set when started from the
+    /// plot legend.
+    fn start_rename(&mut self, ctx: &egui::Context, index: usize, popup_pos: Option<Pos2>) {
+        if let Some(label) = self.gui_conf.plot_options.labels.get(index) {
+            self.renaming = Some(ColumnRename {
+                index,
+                text: label.clone(),
+                popup_pos,
+                error: None,
+            });
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(RENAME_ID)));
+        }
+    }
+
+    /// Shows the text field of the column which is being renamed. Enter applies the name if it
+    /// is valid, escape or clicking somewhere else discards it.
+    fn rename_edit_ui(&mut self, ui: &mut egui::Ui) {
+        let labels = &mut self.gui_conf.plot_options.labels;
+        let Some(rename) = self.renaming.as_mut() else {
+            return;
+        };
+        // the columns changed in the meantime
+        if rename.index >= labels.len() {
+            self.renaming = None;
+            return;
+        }
+        let response = ui.add(
+            TextEdit::singleline(&mut rename.text)
+                .id(egui::Id::new(RENAME_ID))
+                .desired_width(150.0),
+        );
+        if let Some(error) = &rename.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        if !response.lost_focus() {
+            return;
+        }
+        if !ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            self.renaming = None;
+            return;
+        }
+        match validate_column_name(labels, rename.index, &rename.text) {
+            Ok(name) => {
+                labels[rename.index] = name;
+                self.gui_event_tx
+                    .send(GuiEvent::SetNames(labels.clone()))
+                    .expect("Failed to send names");
+                self.renaming = None;
+            }
+            Err(error) => {
+                rename.error = Some(error);
+                response.request_focus();
+            }
+        }
+    }
+
+    /// The name of a series with its unit, e.g. `Temperature [°C]`.
+    fn series_label(&self, i: usize) -> Option<String> {
+        let label = self.gui_conf.plot_options.labels.get(i)?;
+        Some(match self.gui_conf.plot_options.units.get(i) {
+            Some(unit) if !unit.is_empty() => format!("{label} [{unit}]"),
+            _ => label.clone(),
+        })
+    }
+
+    /// The unit shared by all shown series of a panel, `None` if they differ or have none.
+    fn panel_unit(&self, panel: &PlotPanel) -> Option<String> {
+        let plot_options = &self.gui_conf.plot_options;
+        let mut units = (0..self.data.dataset.len())
+            .filter(|i| {
+                plot_options.visible.get(*i).copied().unwrap_or(true)
+                    && panel.series.get(*i).copied().unwrap_or(true)
+            })
+            .map(|i| plot_options.units.get(i).cloned().unwrap_or_default());
+        let unit = units.next()?;
+        (!unit.is_empty() && units.all(|u| u == unit)).then_some(unit)
+    }
+
+    /// The index of the first sample plotted in a panel: the last `plotting_range` samples,
+    /// limited to the last `follow_seconds` before the latest sample when following.
+    fn plot_window(&self, panel: &PlotPanel) -> usize {
+        let window = self.data.dataset[0]
+            .len()
+            .saturating_sub(self.gui_conf.plot_options.plotting_range);
+        match self.data.time.back() {
+            Some(&latest) if panel.follow => {
+                let start = latest.saturating_sub((panel.follow_seconds * 1000.0) as u128);
+                window.max(self.data.time.partition_point(|t| *t < start))
+            }
+            _ => window,
+        }
+    }
+
+    /// Maps a plot x coordinate to the index of the closest sample and its x coordinate.
+    fn hovered_sample(&self, x: f64, window: usize) -> Option<(usize, f64)> {
+        let len = self.data.time.len();
+        let times = match self.gui_conf.plot_options.x_axis {
+            XAxisType::AbsoluteTime => &self.data.absolute_time,
+            _ => &self.data.time,
+        };
+        let idx = match self.gui_conf.plot_options.x_axis {
+            XAxisType::Time | XAxisType::AbsoluteTime => {
+                let t = (x * 1000.0).max(0.0) as u128;
+                let i = times.partition_point(|&time| time < t);
+                // the sample before might be closer
+                if i > 0 && (i == times.len() || t - times[i - 1] < times[i] - t) {
+                    i - 1
+                } else {
+                    i
+                }
+            }
+            XAxisType::Point => (x.round().max(1.0) as usize - 1) + window,
+            // the values of the first column are not sorted
+            XAxisType::FirstData => return None,
+        };
+        if idx < window || idx >= len || idx >= times.len() {
+            return None;
+        }
+        let x = match self.gui_conf.plot_options.x_axis {
+            XAxisType::Time | XAxisType::AbsoluteTime => times[idx] as f64 / 1000.0,
+            _ => (idx - window + 1) as f64,
+        };
+        Some((idx, x))
+    }
+
+    /// The time range of the samples shown in the first plot panel.
+    fn visible_time_range(&self) -> VisibleRange {
+        let plot_options = &self.gui_conf.plot_options;
+        let Some((min, max)) = self.plot_x_bounds else {
+            return VisibleRange::Unsupported;
+        };
+        if plot_options.plot_mode == PlotMode::Xy || plot_options.x_axis == XAxisType::FirstData {
+            return VisibleRange::Unsupported;
+        }
+        let to_ms = |x: f64| (x * 1000.0).max(0.0) as u128;
+        let window = self.plot_window(&plot_options.panels[0]);
+        // the shown samples as index range of the buffer
+        let (first, last) = match plot_options.x_axis {
+            XAxisType::Time => return VisibleRange::Time(to_ms(min), to_ms(max)),
+            XAxisType::AbsoluteTime => (
+                self.data.absolute_time.partition_point(|t| *t < to_ms(min)),
+                self.data
+                    .absolute_time
+                    .partition_point(|t| *t <= to_ms(max)),
+            ),
+            // the points are counted from 1 at the start of the window
+            _ => (
+                window + min.ceil().max(1.0) as usize - 1,
+                window + max.floor().max(0.0) as usize,
+            ),
+        };
+        let last = last.min(self.data.time.len());
+        if first >= last {
+            return VisibleRange::Empty;
+        }
+        VisibleRange::Time(self.data.time[first], self.data.time[last - 1])
+    }
+
+    /// The x coordinate of a marker, if it is in the plotted window.
+    fn marker_x(&self, marker: &Marker, window: usize) -> Option<f64> {
+        // the sample received next after the marker
+        let idx = self
+            .data
+            .time
+            .partition_point(|&t| t < marker.relative_time);
+        if idx < window {
+            return None;
+        }
+        match self.gui_conf.plot_options.x_axis {
+            XAxisType::Time => Some(marker.relative_time as f64 / 1000.0),
+            XAxisType::AbsoluteTime => Some(marker.absolute_time as f64 / 1000.0),
+            XAxisType::Point => Some((idx - window) as f64 + 0.5),
+            // the values of the first column are not sorted
+            XAxisType::FirstData => None,
+        }
+    }
+
+    /// The x coordinate of the trigger sample, if it is in the plotted window.
+    fn trigger_x(&self, window: usize) -> Option<f64> {
+        let idx = self.data.trigger_index().filter(|idx| *idx >= window)?;
+        match self.gui_conf.plot_options.x_axis {
+            XAxisType::Time => Some(self.data.time[idx] as f64 / 1000.0),
+            XAxisType::AbsoluteTime => self.data.absolute_time.get(idx).map(|t| *t as f64 / 1000.0),
+            XAxisType::Point => Some((idx - window + 1) as f64),
+            XAxisType::FirstData => self.data.dataset[0].get(idx).copied(),
+        }
+    }
+
+    fn trigger_ui(&mut self, ui: &mut egui::Ui, spacing: f32) {
+        let mut changed = false;
+        let trigger = &mut self.gui_conf.plot_options.trigger;
+        ui.horizontal(|ui| {
+            ui.label("Trigger: ");
+            ui.add_space(spacing);
+            changed |= ui
+                .add(toggle(&mut trigger.enable))
+                .on_hover_text(
+                    "Freeze the buffer around the sample where the series crosses the level.",
+                )
+                .changed();
+            if trigger.enable {
+                let status = if self.data.trigger_captured {
+                    "captured"
+                } else if self.data.trigger_sample.is_some() {
+                    "triggered"
+                } else {
+                    "armed"
+                };
+                ui.label(status);
+                if ui.button("Re-arm").clicked() {
+                    self.gui_event_tx
+                        .send(GuiEvent::ArmTrigger)
+                        .expect("Failed to send arm trigger");
+                }
+            }
+        });
+        if !trigger.enable {
+            if changed {
+                self.gui_event_tx
+                    .send(GuiEvent::SetTrigger(trigger.clone()))
+                    .expect("Failed to send trigger");
+            }
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Series: ");
+            egui::ComboBox::from_id_source("Trigger Series")
+                .selected_text(
+                    self.gui_conf
+                        .plot_options
+                        .labels
+                        .get(trigger.series)
+                        .cloned()
+                        .unwrap_or_default(),
+                )
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    for (i, label) in self.gui_conf.plot_options.labels.iter().enumerate() {
+                        changed |= ui.selectable_value(&mut trigger.series, i, label).changed();
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            changed |= ui
+                .selectable_value(&mut trigger.edge, TriggerEdge::Rising, "Rising")
+                .changed();
+            changed |= ui
+                .selectable_value(&mut trigger.edge, TriggerEdge::Falling, "Falling")
+                .changed();
+            ui.label("Level: ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut trigger.level).speed(0.1))
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Pre: ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut trigger.pre_samples).clamp_range(0..=1_000_000))
+                .on_hover_text("Samples kept before the trigger.")
+                .changed();
+            ui.label("Post: ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut trigger.post_samples).clamp_range(0..=1_000_000))
+                .on_hover_text("Samples captured after the trigger.")
+                .changed();
+        });
+        if changed {
+            self.gui_event_tx
+                .send(GuiEvent::SetTrigger(trigger.clone()))
+                .expect("Failed to send trigger");
+        }
+    }
+
+    fn alarms_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let alarm_options = &mut self.gui_conf.alarm_options;
+        egui::CollapsingHeader::new("Alarms").show(ui, |ui| {
+            changed |= ui
+                .checkbox(&mut alarm_options.beep, "Beep")
+                .on_hover_text("Play a short tone when an alarm is raised.")
+                .changed();
+            for (i, limits) in alarm_options.limits.iter_mut().enumerate() {
+                let label = self
+                    .gui_conf
+                    .plot_options
+                    .labels
+                    .get(i)
+                    .cloned()
+                    .unwrap_or_default();
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .checkbox(&mut limits.enable, label)
+                        .on_hover_text("Raise an alarm when the series leaves the band.")
+                        .changed();
+                    ui.label("Low: ");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut limits.low).speed(0.1))
+                        .changed();
+                    ui.label("High: ");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut limits.high).speed(0.1))
+                        .changed();
                 });
+            }
+        });
+        if changed {
+            self.gui_event_tx
+                .send(GuiEvent::SetAlarms(alarm_options.clone()))
+                .expect("Failed to send alarms");
+        }
+    }
 
-                self.plot_location = Some(plot_inner.response.rect);
+    fn sample_readout_ui(&self, ui: &mut egui::Ui, idx: usize) {
+        ui.label(format!("t = {:.3} s", self.data.time[idx] as f64 / 1000.0));
+        egui::Grid::new("sample readout").show(ui, |ui| {
+            for (i, set) in self.data.dataset.iter().enumerate() {
+                let visible = self
+                    .gui_conf
+                    .plot_options
+                    .visible
+                    .get(i)
+                    .copied()
+                    .unwrap_or(true);
+                if let (true, Some(value), Some(label)) = (
+                    visible,
+                    set.get(idx),
+                    self.gui_conf.plot_options.labels.get(i),
+                ) {
+                    let unit = self
+                        .gui_conf
+                        .plot_options
+                        .units
+                        .get(i)
+                        .map(String::as_str)
+                        .unwrap_or_default();
+                    ui.label(label);
+                    ui.monospace(format!("{value} {unit}").trim_end());
+                    ui.end_row();
+                }
             }
-        })
+        });
     }
 
     pub fn serial_raw_traffic_ui(&mut self, ui: &mut egui::Ui) {
@@ -489,7 +2499,15 @@ impl MyApp {
         let spacing = 5.0;
         let serial_height = ui.available_size().y - border * 2.0;
 
-        let num_rows = self.data.raw_traffic.len();
+        // the filter only changes what is shown, the raw traffic itself is kept
+        let rows: Vec<usize> = (0..self.data.raw_traffic.len())
+            .filter(|&i| {
+                self.traffic_filter
+                    .matches(&self.data.raw_traffic[i].payload)
+            })
+            .collect();
+        self.traffic_filter.match_count = rows.len();
+        let num_rows = rows.len();
         let row_height = ui.text_style_height(&egui::TextStyle::Body);
 
         let color = if self.gui_conf.dark_mode {
@@ -542,30 +2560,111 @@ impl MyApp {
                             self.gui_conf.raw_traffic_options.show_timestamps =
                                 !self.gui_conf.raw_traffic_options.show_timestamps
                         };
-                        ui.add_space(10.0);
-                        ui.label("EOL character:");
-                        ui.add(
-                            egui::TextEdit::singleline(&mut self.gui_conf.raw_traffic_options.eol)
-                                .desired_width(80.0),
-                        )
-                        .on_hover_text("Configure your EOL character for sent commands..");
 
-                        ui.add_space(10.0);
+                        if ui
+                            .selectable_label(
+                                self.gui_conf.raw_traffic_options.wall_clock_timestamps,
+                                "Wall-Clock Time",
+                            )
+                            .on_hover_text("Show the arrival time as HH:MM:SS.mmm.")
+                            .clicked()
+                        {
+                            self.gui_conf.raw_traffic_options.wall_clock_timestamps =
+                                !self.gui_conf.raw_traffic_options.wall_clock_timestamps
+                        };
 
-                        ui.label("Max Recorded Len:");
                         if ui
-                            .add(egui::DragValue::new(
-                                &mut self.gui_conf.raw_traffic_options.max_len,
-                            ))
-                            .on_hover_text("Select the number of raw traffic to be recorded.")
-                            .changed()
+                            .selectable_label(
+                                self.gui_conf.raw_traffic_options.strip_ansi,
+                                "Strip ANSI",
+                            )
+                            .on_hover_text(
+                                "Remove ANSI escape sequences (e.g. colors) from the received lines.",
+                            )
+                            .clicked()
                         {
+                            self.gui_conf.raw_traffic_options.strip_ansi =
+                                !self.gui_conf.raw_traffic_options.strip_ansi;
+                            self.gui_event_tx
+                                .send(GuiEvent::SetRawTrafficOptions(
+                                    self.gui_conf.raw_traffic_options.clone(),
+                                ))
+                                .expect("Failed to update raw traffic options")
+                        };
+                        ui.add_space(10.0);
+                        ui.label("Line ending:");
+                        let old_line_ending =
+                            self.serial_devices.devices[self.device_idx].line_ending;
+                        egui::ComboBox::from_id_source("Line Ending")
+                            .selected_text(old_line_ending.to_string())
+                            .width(110.0)
+                            .show_ui(ui, |ui| {
+                                for line_ending in [
+                                    LineEnding::None,
+                                    LineEnding::Lf,
+                                    LineEnding::Cr,
+                                    LineEnding::CrLf,
+                                ] {
+                                    ui.selectable_value(
+                                        &mut self.serial_devices.devices[self.device_idx]
+                                            .line_ending,
+                                        line_ending,
+                                        line_ending.to_string(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text("Terminator appended to sent commands.");
+                        let line_ending = self.serial_devices.devices[self.device_idx].line_ending;
+                        if old_line_ending != line_ending {
+                            if let Ok(mut device) = self.device_lock.write() {
+                                device.line_ending = line_ending;
+                            }
+                        }
+
+                        ui.add_space(10.0);
+
+                        ui.label("Max Recorded:");
+                        let options = &mut self.gui_conf.raw_traffic_options;
+                        let mut changed = match options.cap {
+                            TrafficCap::Count => ui
+                                .add(egui::DragValue::new(&mut options.max_len).suffix(" lines"))
+                                .on_hover_text("Select the number of raw traffic to be recorded.")
+                                .changed(),
+                            TrafficCap::Bytes => ui
+                                .add(egui::DragValue::new(&mut options.max_kb).suffix(" KB"))
+                                .on_hover_text(
+                                    "Select the total size of raw traffic to be recorded.",
+                                )
+                                .changed(),
+                        };
+                        changed |= ui
+                            .selectable_value(&mut options.cap, TrafficCap::Count, "Lines")
+                            .changed();
+                        changed |= ui
+                            .selectable_value(&mut options.cap, TrafficCap::Bytes, "Size")
+                            .changed();
+                        ui.label(format!(
+                            "({} used)",
+                            format_bytes(self.data.raw_traffic_bytes as f64)
+                        ))
+                        .on_hover_text("Memory used by the stored raw traffic.");
+                        if changed {
                             self.gui_event_tx
                                 .send(GuiEvent::SetRawTrafficOptions(
                                     self.gui_conf.raw_traffic_options.clone(),
                                 ))
                                 .expect("Failed to update raw traffic options")
                         }
+
+                        ui.add_space(10.0);
+
+                        ui.label("History Len:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.gui_conf.history_size)
+                                .clamp_range(1..=10_000),
+                        )
+                        .on_hover_text("Select the number of sent commands to remember.");
                     });
             });
 
@@ -573,23 +2672,35 @@ impl MyApp {
 
             let width = ui.available_size().x - 2.0 * border;
             ui.vertical_centered_justified(|ui| {
+                ui.horizontal(|ui| {
+                    filter_ui(ui, &mut self.traffic_filter, 200.0);
+                    if ui
+                        .button(egui_phosphor::regular::COPY)
+                        .on_hover_text("Copy all shown lines to the clipboard.")
+                        .clicked()
+                    {
+                        let text: String = rows
+                            .iter()
+                            .flat_map(|&row| self.console_text(&self.data.raw_traffic[row]))
+                            .collect();
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+                });
+                let filter_height = ui.spacing().interact_size.y + spacing;
                 egui::ScrollArea::vertical()
                     .id_source("serial_output")
                     .auto_shrink([false; 2])
                     .stick_to_bottom(true)
                     .enable_scrolling(true)
-                    .max_height(serial_height - 2.0 * spacing)
-                    .min_scrolled_height(serial_height - spacing)
+                    .max_height(serial_height - 2.0 * spacing - filter_height)
+                    .min_scrolled_height(serial_height - spacing - filter_height)
                     .max_width(width)
                     .show_rows(ui, row_height, num_rows, |ui, row_range| {
                         let content: String = row_range
                             .into_iter()
                             .flat_map(|i| {
-                                if self.data.raw_traffic.is_empty() {
-                                    None
-                                } else {
-                                    self.console_text(&self.data.raw_traffic[i])
-                                }
+                                rows.get(i)
+                                    .and_then(|&row| self.console_text(&self.data.raw_traffic[row]))
                             })
                             .collect();
                         ui.add(
@@ -602,51 +2713,109 @@ impl MyApp {
                     });
                 ui.add_space(spacing / 2.0);
                 ui.horizontal(|ui| {
-                    let cmd_line = ui.add(
+                    let send_on_enter = self.gui_conf.raw_traffic_options.send_on_enter;
+                    // without sending on Enter, the Enter key starts a new line of the command
+                    let cmd_edit = if send_on_enter {
                         egui::TextEdit::singleline(&mut self.command)
-                            .desired_width(width - 50.0)
+                    } else {
+                        egui::TextEdit::multiline(&mut self.command).desired_rows(2)
+                    };
+                    let cmd_line = ui.add(
+                        cmd_edit
+                            .desired_width(width - 220.0)
                             .lock_focus(true)
                             .code_editor(),
                     );
                     let cmd_has_lost_focus = cmd_line.lost_focus();
                     let key_pressed = ui.input(|i| i.key_pressed(egui::Key::Enter));
-                    if (key_pressed && cmd_has_lost_focus) || ui.button("Send").clicked() {
+                    ui.checkbox(&mut self.gui_conf.raw_traffic_options.send_hex, "Hex")
+                        .on_hover_text("Send space or comma separated hex bytes, e.g. 0x02 10 FF");
+                    ui.checkbox(
+                        &mut self.gui_conf.raw_traffic_options.send_on_enter,
+                        "Send on Enter",
+                    )
+                    .on_hover_text("Otherwise Enter starts a new line and only the button sends.");
+                    let send_clicked = ui
+                        .add_enabled(self.connected_to_device, egui::Button::new("Send"))
+                        .on_disabled_hover_text("Connect to a device to send commands.")
+                        .clicked();
+                    let enter_sends = send_on_enter && key_pressed && cmd_has_lost_focus;
+                    if send_clicked || (enter_sends && self.connected_to_device) {
                         // send command
                         let command = self
                             .command
                             .clone()
                             .replace("\\r", "\r")
                             .replace("\\n", "\n");
-                        self.history.push(command.clone());
-                        self.index = self.history.len() - 1;
-                        let eol = self
-                            .gui_conf
-                            .raw_traffic_options
-                            .eol
-                            .replace("\\r", "\r")
-                            .replace("\\n", "\n");
-                        if let Err(err) = self.send_tx.send(command.clone() + &eol) {
-                            print_to_console(
-                                &self.print_lock,
-                                Print::Error(format!("send_tx thread send failed: {:?}", err)),
-                            );
+                        // a recalled and edited command becomes a new entry
+                        self.gui_conf.history.push(self.command.clone());
+                        let history_len = self.gui_conf.history.len();
+                        self.gui_conf
+                            .history
+                            .drain(..history_len.saturating_sub(self.gui_conf.history_size));
+                        self.index = self.gui_conf.history.len();
+                        let payload = if self.gui_conf.raw_traffic_options.send_hex {
+                            parse_hex(&command).map(SendPayload::Bytes)
+                        } else {
+                            Ok(SendPayload::Text(command))
+                        };
+                        match payload {
+                            Ok(payload) => {
+                                if let Err(err) = self.send_tx.send(payload) {
+                                    print_to_console(
+                                        &self.print_lock,
+                                        Print::Error(format!(
+                                            "send_tx thread send failed: {:?}",
+                                            err
+                                        )),
+                                    );
+                                }
+                            }
+                            Err(err) => {
+                                print_to_console(&self.print_lock, Print::Error(err));
+                            }
                         }
                         // stay in focus!
                         cmd_line.request_focus();
                     }
-                });
 
-                if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
-                    self.index = self.index.saturating_sub(1);
-                    if !self.history.is_empty() {
-                        self.command = self.history[self.index].clone();
-                    }
-                }
-                if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
-                    self.index = std::cmp::min(self.index + 1, self.history.len() - 1);
-                    if !self.history.is_empty() {
-                        self.command = self.history[self.index].clone();
+                    // the index runs one past the newest entry, which is an empty line, the
+                    // arrow keys move the cursor of a multi-line command instead
+                    if send_on_enter && cmd_line.has_focus() && !self.gui_conf.history.is_empty()
+                    {
+                        let history_len = self.gui_conf.history.len();
+                        let mut recalled = false;
+                        if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
+                            self.index = self.index.min(history_len).saturating_sub(1);
+                            recalled = true;
+                        }
+                        if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
+                            self.index = (self.index + 1).min(history_len);
+                            recalled = true;
+                        }
+                        if recalled {
+                            self.command = self
+                                .gui_conf
+                                .history
+                                .get(self.index)
+                                .cloned()
+                                .unwrap_or_default();
+                        }
                     }
+                });
+
+                // the saved commands double as one-click macro buttons
+                if self.gui_conf.commands.iter().any(|cmd| !cmd.cmd.is_empty()) {
+                    ui.horizontal_wrapped(|ui| {
+                        for cmd in self.gui_conf.commands.iter() {
+                            if cmd.cmd.is_empty() {
+                                continue;
+                            }
+                            if ui.button(&cmd.name).on_hover_text(&cmd.cmd).clicked() {
+                                send_command(&self.send_tx, &self.print_lock, &cmd.cmd);
+                            }
+                        }
+                    });
                 }
             });
         });
@@ -687,6 +2856,322 @@ impl MyApp {
 
                         ui.add_space(linespread);
 
+                        ui.horizontal(|ui| {
+                            ui.label("Delimiter: ");
+                            ui.add_space(spacing);
+
+                            let old_delimiter = self.gui_conf.parsing_options.delimiter;
+                            egui::ComboBox::from_id_source("Delimiter")
+                                .selected_text(self.gui_conf.parsing_options.delimiter.to_string())
+                                .width(90.0)
+                                .show_ui(ui, |ui| {
+                                    for delimiter in [
+                                        Delimiter::CommaColon,
+                                        Delimiter::Comma,
+                                        Delimiter::Tab,
+                                        Delimiter::Semicolon,
+                                        Delimiter::Whitespace,
+                                    ] {
+                                        ui.selectable_value(
+                                            &mut self.gui_conf.parsing_options.delimiter,
+                                            delimiter,
+                                            delimiter.to_string(),
+                                        );
+                                    }
+                                    if ui
+                                        .selectable_label(
+                                            matches!(
+                                                self.gui_conf.parsing_options.delimiter,
+                                                Delimiter::Custom(_)
+                                            ),
+                                            "Custom",
+                                        )
+                                        .clicked()
+                                    {
+                                        self.gui_conf.parsing_options.delimiter =
+                                            Delimiter::Custom(';');
+                                    }
+                                });
+                            if let Delimiter::Custom(c) = self.gui_conf.parsing_options.delimiter {
+                                let mut custom = c.to_string();
+                                if ui
+                                    .add(
+                                        egui::TextEdit::singleline(&mut custom).desired_width(20.0),
+                                    )
+                                    .on_hover_text("Character used to separate the values.")
+                                    .changed()
+                                {
+                                    if let Some(c) = custom.chars().last() {
+                                        self.gui_conf.parsing_options.delimiter =
+                                            Delimiter::Custom(c);
+                                    }
+                                }
+                            }
+                            if old_delimiter != self.gui_conf.parsing_options.delimiter {
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetDelimiter(
+                                        self.gui_conf.parsing_options.delimiter,
+                                    ))
+                                    .expect("Failed to send delimiter");
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Regex: ");
+                            ui.add_space(spacing);
+
+                            let mode_changed = ui
+                                .add(toggle(&mut self.gui_conf.parsing_options.regex_mode))
+                                .on_hover_text(
+                                    "Extract the values with named capture groups instead of \
+                                    splitting, lines which do not match are skipped.",
+                                )
+                                .changed();
+                            let pattern_response = ui.add(
+                                egui::TextEdit::singleline(
+                                    &mut self.gui_conf.parsing_options.regex_pattern,
+                                )
+                                .code_editor()
+                                .desired_width(100.0)
+                                .hint_text(r"temp=(?P<temp>\S+)"),
+                            );
+                            if mode_changed || pattern_response.lost_focus() {
+                                if let Err(e) = self.gui_conf.parsing_options.compile_regex() {
+                                    print_to_console(
+                                        &self.print_lock,
+                                        Print::Error(format!("invalid regex: {e}")),
+                                    );
+                                }
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetRegex(
+                                        self.gui_conf.parsing_options.regex.clone(),
+                                    ))
+                                    .expect("Failed to send regex");
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        self.computed_columns_ui(ui);
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Parse hex: ");
+                            ui.add_space(spacing);
+                            if ui
+                                .add(toggle(&mut self.gui_conf.parsing_options.parse_hex))
+                                .on_hover_text("Also accept integers like 0x1F.")
+                                .changed()
+                            {
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetParseHex(
+                                        self.gui_conf.parsing_options.parse_hex,
+                                    ))
+                                    .expect("Failed to send parse hex");
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Decimal separator: ");
+                            ui.add_space(spacing);
+
+                            let old_separator = self.gui_conf.parsing_options.decimal_separator;
+                            egui::ComboBox::from_id_source("Decimal Separator")
+                                .selected_text(
+                                    self.gui_conf.parsing_options.decimal_separator.to_string(),
+                                )
+                                .width(90.0)
+                                .show_ui(ui, |ui| {
+                                    for separator in
+                                        [DecimalSeparator::Point, DecimalSeparator::Comma]
+                                    {
+                                        ui.selectable_value(
+                                            &mut self.gui_conf.parsing_options.decimal_separator,
+                                            separator,
+                                            separator.to_string(),
+                                        );
+                                    }
+                                });
+                            let parsing_options = &mut self.gui_conf.parsing_options;
+                            if old_separator != parsing_options.decimal_separator {
+                                // a comma can not separate both the values and the decimals
+                                if parsing_options.decimal_separator == DecimalSeparator::Comma
+                                    && matches!(
+                                        parsing_options.delimiter,
+                                        Delimiter::CommaColon | Delimiter::Comma
+                                    )
+                                {
+                                    parsing_options.delimiter = Delimiter::Semicolon;
+                                    self.gui_event_tx
+                                        .send(GuiEvent::SetDelimiter(parsing_options.delimiter))
+                                        .expect("Failed to send delimiter");
+                                }
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetDecimalSeparator(
+                                        parsing_options.decimal_separator,
+                                    ))
+                                    .expect("Failed to send decimal separator");
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Missing values: ");
+                            ui.add_space(spacing);
+
+                            let old_missing = self.gui_conf.parsing_options.missing_fields;
+                            egui::ComboBox::from_id_source("Missing Fields")
+                                .selected_text(old_missing.to_string())
+                                .width(90.0)
+                                .show_ui(ui, |ui| {
+                                    for missing in [
+                                        MissingFields::Strict,
+                                        MissingFields::CarryForward,
+                                        MissingFields::NanFill,
+                                    ] {
+                                        ui.selectable_value(
+                                            &mut self.gui_conf.parsing_options.missing_fields,
+                                            missing,
+                                            missing.to_string(),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text(
+                                    "Lines with fewer values than the dataset, e.g. from devices \
+                                    reporting channels at different rates.",
+                                );
+                            let missing = self.gui_conf.parsing_options.missing_fields;
+                            if old_missing != missing {
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetMissingFields(missing))
+                                    .expect("Failed to send missing fields");
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Device time: ");
+                            ui.add_space(spacing);
+                            let enable_changed = ui
+                                .add(toggle(&mut self.gui_conf.parsing_options.device_time))
+                                .on_hover_text(
+                                    "Use a column with the time of the device (in ms) as the time \
+                                    axis instead of the arrival time of the line.",
+                                )
+                                .changed();
+                            let column_changed = ui
+                                .add_enabled(
+                                    self.gui_conf.parsing_options.device_time,
+                                    egui::DragValue::new(
+                                        &mut self.gui_conf.parsing_options.time_column,
+                                    )
+                                    .clamp_range(0..=100)
+                                    .prefix("column "),
+                                )
+                                .changed();
+                            if enable_changed || column_changed {
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetDeviceTime(
+                                        self.gui_conf.parsing_options.device_time,
+                                        self.gui_conf.parsing_options.time_column,
+                                    ))
+                                    .expect("Failed to send device time");
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Reset after [#]: ");
+                            ui.add_space(spacing);
+
+                            let mismatches = ui
+                                .add(
+                                    egui::DragValue::new(
+                                        &mut self.gui_conf.parsing_options.reset_after_mismatches,
+                                    )
+                                    .clamp_range(1..=10_000),
+                                )
+                                .on_hover_text(
+                                    "Lines with a different number of values are dropped, \
+                                    after this many in a row the dataset is reset.",
+                                )
+                                .changed();
+                            let stable_lines = ui
+                                .add(
+                                    egui::DragValue::new(
+                                        &mut self.gui_conf.parsing_options.reset_stable_lines,
+                                    )
+                                    .clamp_range(1..=10_000),
+                                )
+                                .on_hover_text(
+                                    "The new number of values needs to be stable for this many lines.",
+                                )
+                                .changed();
+                            if mismatches || stable_lines {
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetResetLimits(
+                                        self.gui_conf.parsing_options.reset_after_mismatches,
+                                        self.gui_conf.parsing_options.reset_stable_lines,
+                                    ))
+                                    .expect("Failed to send reset limits");
+                            }
+                        });
+                        ui.label(format!(
+                            "Mismatched: {} / {} ({} dropped)",
+                            self.data.mismatched_lines,
+                            self.gui_conf.parsing_options.reset_after_mismatches,
+                            self.data.dropped_lines
+                        ))
+                        .on_hover_text(
+                            "Consecutive lines with a different number of values, \
+                            the dataset is reset when the limit is reached.",
+                        );
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Rate: {:.1} samples/s", self.data.samples_per_second));
+                            if self.data.lagging {
+                                ui.colored_label(
+                                    egui::Color32::RED,
+                                    format!("{} packets behind", self.data.backlog),
+                                )
+                                .on_hover_text(
+                                    "The data arrives faster than it can be parsed, \
+                                    consider a lower rate.",
+                                );
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Header line: ");
+                            ui.add_space(spacing);
+
+                            if ui
+                                .add(toggle(&mut self.gui_conf.plot_options.header_mode))
+                                .on_hover_text(
+                                    "Read the column names from a non-numeric header line.",
+                                )
+                                .changed()
+                            {
+                                self.gui_event_tx
+                                    .send(GuiEvent::SetHeaderMode(
+                                        self.gui_conf.plot_options.header_mode,
+                                    ))
+                                    .expect("Failed to send header mode");
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
                         ui.horizontal(|ui| {
                             ui.label("Plotting range [#]: ");
                             ui.add_space(spacing);
@@ -708,43 +3193,10 @@ impl MyApp {
                     );
                             if ui
                                 .button("Full Dataset")
-                                .on_hover_text("Show the full dataset.")
-                                .clicked()
-                            {
-                                self.gui_conf.plot_options.plotting_range = usize::MAX;
-                            }
-                        });
-
-                        ui.add_space(linespread);
-
-                        ui.horizontal(|ui| {
-                            ui.label("Number of plots [#]: ");
-                            ui.add_space(spacing);
-
-                            if ui
-                                .button(egui::RichText::new(
-                                    egui_phosphor::regular::ARROW_FAT_LEFT.to_string(),
-                                ))
-                                .clicked()
-                            {
-                                self.gui_conf.plot_options.number_of_plots =
-                                    (self.gui_conf.plot_options.number_of_plots - 1).clamp(1, 10);
-                            }
-                            ui.add(
-                                egui::DragValue::new(
-                                    &mut self.gui_conf.plot_options.number_of_plots,
-                                )
-                                .clamp_range(1..=10),
-                            )
-                            .on_hover_text("Select the number of plots to be shown.");
-                            if ui
-                                .button(egui::RichText::new(
-                                    egui_phosphor::regular::ARROW_FAT_RIGHT.to_string(),
-                                ))
+                                .on_hover_text("Show the full dataset.")
                                 .clicked()
                             {
-                                self.gui_conf.plot_options.number_of_plots =
-                                    (self.gui_conf.plot_options.number_of_plots + 1).clamp(1, 10);
+                                self.gui_conf.plot_options.plotting_range = usize::MAX;
                             }
                         });
 
@@ -761,20 +3213,204 @@ impl MyApp {
                                 &mut self.gui_conf.plot_options.x_axis,
                                 XAxisType::Time,
                                 "Time",
-                            );
+                            )
+                            .on_hover_text("Seconds since the connection was opened.");
+                            ui.selectable_value(
+                                &mut self.gui_conf.plot_options.x_axis,
+                                XAxisType::AbsoluteTime,
+                                "Clock",
+                            )
+                            .on_hover_text("Wall-clock time (HH:MM:SS) of the host.");
                             ui.selectable_value(
                                 &mut self.gui_conf.plot_options.x_axis,
                                 XAxisType::FirstData,
                                 "First Data",
                             );
-                        })
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.label("Y Axis:");
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(
+                                &mut self.gui_conf.plot_options.y_axis,
+                                YAxisScale::Linear,
+                                "Linear",
+                            );
+                            ui.selectable_value(
+                                &mut self.gui_conf.plot_options.y_axis,
+                                YAxisScale::Log10,
+                                "Log10",
+                            )
+                            .on_hover_text("Non-positive values are not shown.");
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.label("Y Range:");
+                        ui.horizontal(|ui| {
+                            let plot_options = &mut self.gui_conf.plot_options;
+                            ui.selectable_value(
+                                &mut plot_options.y_range,
+                                YAxisRange::Full,
+                                "Full Auto",
+                            )
+                            .on_hover_text("From the minimum to the maximum of the data.");
+                            ui.selectable_value(
+                                &mut plot_options.y_range,
+                                YAxisRange::Percentile,
+                                "Percentile",
+                            )
+                            .on_hover_text(
+                                "Between two percentiles of the visible data, so that outliers \
+                                do not compress the trace.",
+                            );
+                            ui.selectable_value(
+                                &mut plot_options.y_range,
+                                YAxisRange::Manual,
+                                "Manual",
+                            )
+                            .on_hover_text(
+                                "Fixed limits, e.g. for comparable screenshots. A limit which is \
+                                not set follows the data.",
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            let plot_options = &mut self.gui_conf.plot_options;
+                            match plot_options.y_range {
+                                YAxisRange::Full => {}
+                                YAxisRange::Percentile => {
+                                    let percentile = plot_options.y_percentile;
+                                    ui.add(
+                                        egui::DragValue::new(&mut plot_options.y_percentile)
+                                            .clamp_range(0.0..=49.0)
+                                            .speed(0.1),
+                                    );
+                                    ui.label(format!("to {} percentile", 100.0 - percentile));
+                                }
+                                YAxisRange::Manual => {
+                                    let [min_locked, max_locked] = &mut plot_options.y_locked;
+                                    let [min, max] = &mut plot_options.y_limits;
+                                    ui.checkbox(min_locked, "Min");
+                                    ui.add_enabled(
+                                        *min_locked,
+                                        egui::DragValue::new(min).speed(0.1),
+                                    );
+                                    ui.checkbox(max_locked, "Max");
+                                    ui.add_enabled(
+                                        *max_locked,
+                                        egui::DragValue::new(max).speed(0.1),
+                                    );
+                                }
+                            }
+                            if plot_options.y_range != YAxisRange::Full
+                                && ui.button("Reset to Auto").clicked()
+                            {
+                                plot_options.y_range = YAxisRange::Full;
+                            }
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Line width: ");
+                            ui.add_space(spacing);
+                            ui.add(egui::Slider::new(
+                                &mut self.gui_conf.plot_options.line_width,
+                                0.5..=5.0,
+                            ));
+                        });
+                        ui.checkbox(&mut self.gui_conf.plot_options.show_markers, "Point markers")
+                            .on_hover_text("Mark the single samples, useful for sparse data.");
+                        ui.checkbox(
+                            &mut self.gui_conf.plot_options.show_command_markers,
+                            "Command markers",
+                        )
+                        .on_hover_text("Vertical lines at the commands sent to the device.");
+                        ui.add_enabled(
+                            self.reference.is_some(),
+                            egui::Checkbox::new(
+                                &mut self.gui_conf.plot_options.show_reference,
+                                "Reference",
+                            ),
+                        )
+                        .on_hover_text("Show the captured reference as dashed lines.");
+                        ui.horizontal(|ui| {
+                            ui.label("Max points: ");
+                            ui.add_space(spacing);
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut self.gui_conf.plot_options.max_plot_points,
+                                    100..=100_000,
+                                )
+                                .logarithmic(true),
+                            )
+                            .on_hover_text(
+                                "Denser series are reduced to the minimum and maximum per bucket \
+                                when drawn, the buffer and the exports keep all samples.",
+                            );
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.label("Plot Mode:");
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(
+                                &mut self.gui_conf.plot_options.plot_mode,
+                                PlotMode::TimeSeries,
+                                "Time Series",
+                            );
+                            ui.selectable_value(
+                                &mut self.gui_conf.plot_options.plot_mode,
+                                PlotMode::Xy,
+                                "XY",
+                            )
+                            .on_hover_text("Plot one series against another.");
+                        });
+                        if self.gui_conf.plot_options.plot_mode == PlotMode::Xy {
+                            let labels = self.gui_conf.plot_options.labels.clone();
+                            let plot_options = &mut self.gui_conf.plot_options;
+                            for (text, series) in [
+                                ("X: ", &mut plot_options.xy_x_series),
+                                ("Y: ", &mut plot_options.xy_y_series),
+                            ] {
+                                ui.horizontal(|ui| {
+                                    ui.label(text);
+                                    egui::ComboBox::from_id_source(format!("XY Series {text}"))
+                                        .selected_text(
+                                            labels.get(*series).cloned().unwrap_or_default(),
+                                        )
+                                        .width(100.0)
+                                        .show_ui(ui, |ui| {
+                                            for i in 0..self.data.dataset.len() {
+                                                if let Some(label) = labels.get(i) {
+                                                    ui.selectable_value(series, i, label);
+                                                }
+                                            }
+                                        });
+                                });
+                            }
+                        }
+
+                        ui.add_space(linespread);
+
+                        self.trigger_ui(ui, spacing);
+
+                        ui.add_space(linespread);
+
+                        self.alarms_ui(ui);
                     });
             });
             ui.separator();
             ui.vertical(|ui| {
-                if ui.button("Reset Labels").clicked() {
-                    self.gui_conf.plot_options.labels = self.data.names.clone();
-                }
+                ui.horizontal(|ui| {
+                    if ui.button("Reset Labels").clicked() {
+                        self.gui_conf.plot_options.labels = self.data.names.clone();
+                        self.renaming = None;
+                    }
+                    ui.checkbox(&mut self.gui_conf.plot_options.show_sparklines, "Sparklines")
+                        .on_hover_text("Show the latest samples of each Dataset next to its name.");
+                });
                 ui.add_space(linespread);
                 if self.data.names.len() == 1 {
                     ui.label("Detected 1 Dataset:");
@@ -800,20 +3436,84 @@ impl MyApp {
                         // break;
                     }
 
-                    if ui
-                        .add(
-                            egui::TextEdit::singleline(&mut self.gui_conf.plot_options.labels[i])
-                                .desired_width(150.0),
-                        )
-                        .on_hover_text("Use custom names for your Datasets.")
-                        .changed()
-                    {
-                        self.gui_event_tx
-                            .send(GuiEvent::SetNames(
-                                self.gui_conf.plot_options.labels.clone(),
-                            ))
-                            .expect("Failed to send names");
-                    };
+                    ui.horizontal(|ui| {
+                        if let Some(visible) = self.gui_conf.plot_options.visible.get_mut(i) {
+                            ui.checkbox(visible, "")
+                                .on_hover_text("Show this Dataset in the plot.");
+                        }
+                        if self.gui_conf.plot_options.show_sparklines {
+                            let color = self
+                                .gui_conf
+                                .plot_options
+                                .colors
+                                .get(i)
+                                .copied()
+                                .flatten()
+                                .unwrap_or_else(|| auto_color(i));
+                            if let Some(set) = self.data.dataset.get(i) {
+                                let start = set.len().saturating_sub(SPARKLINE_SAMPLES);
+                                let latest: Vec<f64> = set.range(start..).copied().collect();
+                                sparkline(ui, &latest, color);
+                            }
+                        }
+                        if let Some(color) = self.gui_conf.plot_options.colors.get_mut(i) {
+                            let mut picked = color.unwrap_or_else(|| auto_color(i));
+                            let response = egui::color_picker::color_edit_button_srgba(
+                                ui,
+                                &mut picked,
+                                egui::color_picker::Alpha::Opaque,
+                            )
+                            .on_hover_text("Pick a color, right click to reset.");
+                            if response.changed() {
+                                *color = Some(picked);
+                            }
+                            if response.secondary_clicked() {
+                                *color = None;
+                            }
+                        }
+                        let editing = self
+                            .renaming
+                            .as_ref()
+                            .is_some_and(|r| r.index == i && r.popup_pos.is_none());
+                        if editing {
+                            self.rename_edit_ui(ui);
+                        } else if ui
+                            .add(
+                                egui::Label::new(&self.gui_conf.plot_options.labels[i])
+                                    .sense(Sense::click()),
+                            )
+                            .on_hover_text("Double-click to rename this Dataset.")
+                            .double_clicked()
+                        {
+                            self.start_rename(ui.ctx(), i, None);
+                        }
+                        let plot_options = &mut self.gui_conf.plot_options;
+                        if let Some(unit) = plot_options.units.get_mut(i) {
+                            ui.add(
+                                egui::TextEdit::singleline(unit)
+                                    .desired_width(40.0)
+                                    .hint_text("unit"),
+                            )
+                            .on_hover_text("Unit of this Dataset, shown in the plot.");
+                        }
+                        if let (Some(scale), Some(offset)) =
+                            (plot_options.scales.get_mut(i), plot_options.offsets.get_mut(i))
+                        {
+                            ui.add(egui::DragValue::new(scale).speed(0.01).prefix("×"))
+                                .on_hover_text("Display gain of this Dataset.");
+                            ui.add(egui::DragValue::new(offset).speed(0.1).prefix("+"))
+                                .on_hover_text("Display offset of this Dataset.");
+                            if (*scale != 1.0 || *offset != 0.0)
+                                && ui
+                                    .small_button(egui_phosphor::regular::ARROW_COUNTER_CLOCKWISE)
+                                    .on_hover_text("Reset the gain and offset.")
+                                    .clicked()
+                            {
+                                *scale = 1.0;
+                                *offset = 0.0;
+                            }
+                        }
+                    });
                 }
                 if self.data.names.len() > 10 {
                     ui.label("Only renaming up to 10 Datasets is currently supported.");
@@ -852,19 +3552,24 @@ impl MyApp {
                     .record_options
                     .record_path
                     .set_extension("csv");
-                if !self.gui_conf.record_options.enable
-                    && self.gui_conf.record_options.record_path.exists()
-                {
+                let record_options = &self.gui_conf.record_options;
+                let existing_file = [
+                    (
+                        record_options.record_values,
+                        record_options.record_path.clone(),
+                    ),
+                    (record_options.record_raw, record_options.raw_path(0)),
+                ]
+                .into_iter()
+                .find(|(enabled, path)| *enabled && path.exists())
+                .map(|(_, path)| path);
+                if let (false, Some(path)) = (record_options.enable, existing_file) {
                     ready = false;
                     let result = MessageDialog::new()
                         .set_title("Overwrite confirm")
                         .set_description(format!(
                             "File {} exists. Do you want to overwrite it?",
-                            self.gui_conf
-                                .record_options
-                                .record_path
-                                .to_str()
-                                .unwrap_or_default()
+                            path.to_str().unwrap_or_default()
                         ))
                         .set_buttons(rfd::MessageButtons::YesNo)
                         .show();
@@ -947,6 +3652,307 @@ impl MyApp {
                     !self.gui_conf.record_options.windows_style_line_endings
             }
         });
+        ui.add_space(LINESPREAD);
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(self.gui_conf.record_options.record_values, "Record values")
+                .on_hover_text("Write the parsed values to the CSV file.")
+                .clicked()
+            {
+                self.gui_conf.record_options.record_values =
+                    !self.gui_conf.record_options.record_values
+            }
+            ui.add_space(SPACE);
+            if ui
+                .selectable_label(self.gui_conf.record_options.record_raw, "Record raw bytes")
+                .on_hover_text(
+                    "Write the received and sent bytes verbatim to a .bin file, the timestamps, \
+                    offsets and lengths go to a .timestamps.csv file next to it.",
+                )
+                .clicked()
+            {
+                self.gui_conf.record_options.record_raw = !self.gui_conf.record_options.record_raw
+            }
+        });
+        ui.add_space(LINESPREAD);
+        ui.horizontal(|ui| {
+            ui.label("New file after: ");
+            ui.add(
+                egui::DragValue::new(&mut self.gui_conf.record_options.rotate_size_mb)
+                    .clamp_range(0..=100_000)
+                    .suffix(" MB"),
+            )
+            .on_hover_text("0 keeps writing to a single file.");
+            ui.label("or");
+            ui.add(
+                egui::DragValue::new(&mut self.gui_conf.record_options.rotate_minutes)
+                    .clamp_range(0..=100_000)
+                    .suffix(" min"),
+            )
+            .on_hover_text("0 keeps writing to a single file.");
+        });
+        ui.add_space(LINESPREAD);
+        ui.horizontal(|ui| {
+            ui.label("Record every: ");
+            ui.add(
+                egui::DragValue::new(&mut self.gui_conf.record_options.decimation)
+                    .clamp_range(1..=1_000_000)
+                    .suffix(" samples"),
+            )
+            .on_hover_text("Record one row per this many samples, the plot keeps all of them.");
+            ui.checkbox(&mut self.gui_conf.record_options.average, "Average")
+                .on_hover_text(
+                    "Record the mean of the samples, at the time of the center of the bucket.",
+                );
+        });
+    }
+
+    /// The latest value of each series in large text, like a panel meter.
+    pub fn readout_ui(&mut self, ui: &mut egui::Ui) {
+        let spacing = 10.0;
+        let linespread = 5.0;
+        ui.horizontal(|ui| {
+            ui.heading("Readout");
+            ui.add_space(spacing);
+            let readout_options = &mut self.gui_conf.readout_options;
+            ui.label("Decimals: ");
+            ui.add(egui::DragValue::new(&mut readout_options.decimals).clamp_range(0..=10));
+            ui.add_space(spacing);
+            ui.label("Size: ");
+            ui.add(egui::Slider::new(
+                &mut readout_options.text_size,
+                12.0..=120.0,
+            ));
+        });
+        ui.add_space(linespread);
+        egui::ScrollArea::vertical()
+            .id_source("readout scroll")
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    let readout_options = &self.gui_conf.readout_options;
+                    for (i, (set, unit)) in self
+                        .data
+                        .dataset
+                        .iter()
+                        .zip(self.gui_conf.plot_options.units.iter_mut())
+                        .enumerate()
+                    {
+                        let visible = self
+                            .gui_conf
+                            .plot_options
+                            .visible
+                            .get(i)
+                            .copied()
+                            .unwrap_or(true);
+                        let Some(label) = self.gui_conf.plot_options.labels.get(i) else {
+                            continue;
+                        };
+                        if !visible {
+                            continue;
+                        }
+                        let value = match set.back() {
+                            Some(value) => {
+                                format!("{:.*} {unit}", readout_options.decimals, value)
+                            }
+                            None => "--".to_string(),
+                        };
+                        let mut text = egui::RichText::new(value)
+                            .monospace()
+                            .size(readout_options.text_size);
+                        // the same warning as the flashing series in the plot
+                        if self.data.alarms_active.get(i).copied().unwrap_or(false) {
+                            text = text.color(egui::Color32::RED);
+                        }
+                        egui::Frame::group(ui.style()).show(ui, |ui| {
+                            ui.vertical(|ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label(label);
+                                    ui.add(
+                                        egui::TextEdit::singleline(unit)
+                                            .desired_width(40.0)
+                                            .hint_text("unit"),
+                                    );
+                                });
+                                ui.label(text);
+                            });
+                        });
+                    }
+                });
+            });
+    }
+
+    /// The offset and a copy of the received bytes of the active connection, for freezing.
+    fn byte_log(&self) -> (usize, Vec<u8>) {
+        self.byte_log_lock
+            .read()
+            .map(|log| (log.offset, log.bytes.iter().copied().collect()))
+            .unwrap_or_default()
+    }
+
+    /// The received bytes before they are split into lines, 16 per row with their offset and
+    /// as ASCII, for protocols which are not plain text.
+    pub fn hex_dump_ui(&mut self, ui: &mut egui::Ui) {
+        let spacing = 10.0;
+        let linespread = 5.0;
+        ui.horizontal(|ui| {
+            ui.heading("Hex Dump");
+            ui.add_space(spacing);
+            let mut frozen = self.hex_dump_frozen.is_some();
+            if ui
+                .checkbox(&mut frozen, "Freeze")
+                .on_hover_text(
+                    "Keep the shown bytes to scroll through them, the reception continues.",
+                )
+                .changed()
+            {
+                self.hex_dump_frozen = frozen.then(|| self.byte_log());
+            }
+        });
+        ui.add_space(linespread);
+        // the live bytes are read under the lock, only the rows in view are copied
+        let frozen = self.hex_dump_frozen.as_ref();
+        let live = match frozen {
+            Some(_) => None,
+            None => self.byte_log_lock.read().ok(),
+        };
+        let (offset, len) = match (frozen, &live) {
+            (Some((offset, bytes)), _) => (*offset, bytes.len()),
+            (None, Some(log)) => (log.offset, log.bytes.len()),
+            (None, None) => (0, 0),
+        };
+        ui.label(format!("{len} bytes shown, {} received", offset + len));
+        ui.add_space(linespread);
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let num_rows = len.div_ceil(HEX_DUMP_ROW_BYTES);
+        egui::ScrollArea::vertical()
+            .id_source("hex dump scroll")
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, num_rows, |ui, row_range| {
+                for row in row_range {
+                    let start = row * HEX_DUMP_ROW_BYTES;
+                    let end = (start + HEX_DUMP_ROW_BYTES).min(len);
+                    let row_bytes: Vec<u8> = match (frozen, &live) {
+                        (Some((_, bytes)), _) => bytes[start..end].to_vec(),
+                        (None, Some(log)) => log.bytes.range(start..end).copied().collect(),
+                        (None, None) => vec![],
+                    };
+                    ui.monospace(hex_dump_row(offset + start, &row_bytes));
+                }
+            });
+    }
+
+    pub fn fft_ui(&mut self, ui: &mut egui::Ui) {
+        let spacing = 10.0;
+        let linespread = 5.0;
+        let estimated_sample_rate = estimate_sample_rate(&self.data.time);
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| {
+                ui.heading("FFT");
+                ui.add_space(linespread);
+                egui::ScrollArea::vertical()
+                    .id_source("fft options scroll")
+                    .max_width(200.0)
+                    .auto_shrink([false; 2])
+                    .show(ui, |ui| {
+                        let fft_options = &mut self.gui_conf.fft_options;
+                        ui.horizontal(|ui| {
+                            ui.label("Series: ");
+                            ui.add_space(spacing);
+                            let selected = self
+                                .gui_conf
+                                .plot_options
+                                .labels
+                                .get(fft_options.series)
+                                .cloned()
+                                .unwrap_or_default();
+                            egui::ComboBox::from_id_source("FFT Series")
+                                .selected_text(selected)
+                                .width(100.0)
+                                .show_ui(ui, |ui| {
+                                    for i in 0..self.data.dataset.len() {
+                                        if let Some(label) =
+                                            self.gui_conf.plot_options.labels.get(i)
+                                        {
+                                            ui.selectable_value(&mut fft_options.series, i, label);
+                                        }
+                                    }
+                                });
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("FFT size [#]: ");
+                            ui.add_space(spacing);
+                            egui::ComboBox::from_id_source("FFT Size")
+                                .selected_text(fft_options.fft_size.to_string())
+                                .width(70.0)
+                                .show_ui(ui, |ui| {
+                                    for size in [256, 512, 1024, 2048, 4096, 8192, 16384] {
+                                        ui.selectable_value(
+                                            &mut fft_options.fft_size,
+                                            size,
+                                            size.to_string(),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Shorter buffers are zero-padded.");
+                        });
+
+                        ui.add_space(linespread);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Manual rate: ");
+                            ui.add_space(spacing);
+                            ui.add(toggle(&mut fft_options.manual_sample_rate))
+                                .on_hover_text(
+                                    "Otherwise the rate is estimated from the median time step.",
+                                );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Sample rate [Hz]: ");
+                            ui.add_space(spacing);
+                            if fft_options.manual_sample_rate {
+                                ui.add(
+                                    egui::DragValue::new(&mut fft_options.sample_rate)
+                                        .clamp_range(0.001..=f64::MAX)
+                                        .speed(1.0),
+                                );
+                            } else {
+                                match estimated_sample_rate {
+                                    Some(rate) => ui.label(format!("{rate:.2}")),
+                                    None => ui.label("-"),
+                                };
+                            }
+                        });
+                    });
+            });
+            ui.separator();
+
+            let fft_options = &self.gui_conf.fft_options;
+            let sample_rate = if fft_options.manual_sample_rate {
+                Some(fft_options.sample_rate)
+            } else {
+                estimated_sample_rate
+            };
+            let spectrum = match (self.data.dataset.get(fft_options.series), sample_rate) {
+                (Some(values), Some(rate)) => {
+                    magnitude_spectrum(&mut self.fft_planner, values, fft_options.fft_size, rate)
+                }
+                _ => vec![],
+            };
+            Plot::new("fft")
+                .height(ui.available_height())
+                .width(ui.available_width())
+                .x_axis_label("Frequency [Hz]")
+                .y_axis_label("Magnitude")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(PlotPoints::from(spectrum)));
+                });
+        });
     }
 
     pub fn commands_gui(&mut self, ui: &mut egui::Ui) {
@@ -987,18 +3993,8 @@ impl MyApp {
                                         .clip_text(true)
                                         .desired_width(ui.available_width() - 90.0),
                                 );
-                                let send_cmd =
-                                    cmd.cmd.clone().replace("\\r", "\r").replace("\\n", "\n");
                                 if ui.button("Send").clicked() {
-                                    if let Err(err) = self.send_tx.send(send_cmd) {
-                                        print_to_console(
-                                            &self.print_lock,
-                                            Print::Error(format!(
-                                                "send_tx thread send failed: {:?}",
-                                                err
-                                            )),
-                                        );
-                                    }
+                                    send_command(&self.send_tx, &self.print_lock, &cmd.cmd);
                                 }
                                 !ui.button("Del").clicked()
                             })
@@ -1017,6 +4013,139 @@ impl MyApp {
                         editing: false,
                     })
                 };
+                ui.add_space(LINESPREAD);
+                ui.separator();
+                self.sequence_ui(ui);
+                ui.separator();
+                self.send_file_ui(ui);
             });
     }
+
+    /// A multi-line box whose lines are sent one after the other by the serial thread.
+    fn sequence_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Sequence");
+        let sequence = &mut self.gui_conf.sequence;
+        ui.add(
+            TextEdit::multiline(&mut sequence.lines)
+                .code_editor()
+                .desired_rows(5)
+                .desired_width(ui.available_width())
+                .hint_text("one command per line"),
+        );
+        ui.horizontal(|ui| {
+            ui.label("Delay [ms]: ");
+            ui.add(egui::DragValue::new(&mut sequence.delay_ms).clamp_range(0..=60_000))
+                .on_hover_text("Time between two lines, to let the device process each.");
+            ui.label("Repeat: ");
+            ui.add(egui::DragValue::new(&mut sequence.repeat).clamp_range(1..=10_000));
+        });
+        let running = self.sequence_end.is_some_and(|end| end > Instant::now());
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!running, Button::new("Run Sequence"))
+                .clicked()
+            {
+                let lines: Vec<String> = sequence
+                    .lines
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| line.replace("\\r", "\r").replace("\\n", "\n"))
+                    .collect();
+                let delay = Duration::from_millis(sequence.delay_ms);
+                let count = (lines.len() * sequence.repeat) as u32;
+                self.sequence_end = Some(Instant::now() + delay * count.saturating_sub(1));
+                if let Err(err) = self.send_tx.send(SendPayload::Sequence {
+                    lines,
+                    delay,
+                    repeat: sequence.repeat,
+                }) {
+                    print_to_console(
+                        &self.print_lock,
+                        Print::Error(format!("send_tx thread send failed: {:?}", err)),
+                    );
+                }
+            }
+            if ui.add_enabled(running, Button::new("Stop")).clicked() {
+                self.sequence_end = None;
+                self.send_tx
+                    .send(SendPayload::StopSequence)
+                    .unwrap_or_default();
+            }
+            if running {
+                ui.label("running..");
+            }
+        });
+    }
+
+    /// Streams a text or binary file to the device in chunks, with the progress of a running
+    /// transfer.
+    fn send_file_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Send File");
+        let options = &mut self.gui_conf.send_file;
+        ui.horizontal(|ui| {
+            ui.label("Chunk: ");
+            ui.add(
+                egui::DragValue::new(&mut options.chunk_size)
+                    .clamp_range(1..=65_536)
+                    .suffix(" bytes"),
+            );
+            ui.label("Delay [ms]: ");
+            ui.add(egui::DragValue::new(&mut options.delay_ms).clamp_range(0..=60_000))
+                .on_hover_text("Pause after each chunk, to let the device keep up.");
+        });
+        let transfer = self.connection_status.transfer;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    self.connected_to_device && transfer.is_none(),
+                    Button::new("Send File"),
+                )
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    match std::fs::read(&path) {
+                        Ok(bytes) => {
+                            let name = path
+                                .file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            if let Err(err) = self.send_tx.send(SendPayload::File {
+                                name,
+                                bytes,
+                                chunk_size: options.chunk_size,
+                                delay: Duration::from_millis(options.delay_ms),
+                            }) {
+                                print_to_console(
+                                    &self.print_lock,
+                                    Print::Error(format!("send_tx thread send failed: {:?}", err)),
+                                );
+                            }
+                        }
+                        Err(e) => print_to_console(
+                            &self.print_lock,
+                            Print::Error(format!("could not read {:?}: {e}", path)),
+                        ),
+                    }
+                }
+            }
+            if ui
+                .add_enabled(transfer.is_some(), Button::new("Cancel"))
+                .clicked()
+            {
+                self.send_tx
+                    .send(SendPayload::CancelFile)
+                    .unwrap_or_default();
+                print_to_console(
+                    &self.print_lock,
+                    Print::Ok("Cancelled sending the file".to_string()),
+                );
+            }
+        });
+        if let Some((sent, total)) = transfer {
+            ui.add(
+                egui::ProgressBar::new(sent as f32 / total.max(1) as f32)
+                    .text(format!("{sent} / {total} bytes")),
+            );
+        }
+    }
 }
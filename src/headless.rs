@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::gui::Print;
+use crate::record::RecordOptions;
+use crate::serial::{devices_thread, load_serial_settings, PortInfo, TCP_PREFIX};
+use crate::spawn_connection;
+
+pub const USAGE: &str = "usage: serial-monitor-rust --headless --port <port> --output <file.csv> \
+[--baud <baud>] [--duration <seconds>] [--lines <count>]";
+
+/// Settings of a logging run without the GUI, read from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessOptions {
+    /// serial port name or `tcp://host:port`
+    pub port: String,
+    pub baud: Option<u32>,
+    pub output: PathBuf,
+    /// stop after this time, otherwise the logging runs until Ctrl-C is pressed
+    pub duration: Option<Duration>,
+    /// stop after this many samples
+    pub lines: Option<usize>,
+}
+
+impl HeadlessOptions {
+    /// Parses the arguments following the program name, `--headless` itself is skipped.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut port = None;
+        let mut baud = None;
+        let mut output = None;
+        let mut duration = None;
+        let mut lines = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--headless" {
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {arg}"))?;
+            let invalid = || format!("invalid value for {arg}: {value:?}");
+            match arg.as_str() {
+                "--port" => port = Some(value.clone()),
+                "--baud" => baud = Some(value.parse().map_err(|_| invalid())?),
+                "--output" => output = Some(PathBuf::from(value)),
+                "--duration" => {
+                    duration = Some(Duration::from_secs_f64(
+                        value.parse::<f64>().map_err(|_| invalid())?.max(0.0),
+                    ))
+                }
+                "--lines" => lines = Some(value.parse().map_err(|_| invalid())?),
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        Ok(Self {
+            port: port.ok_or("--port is required")?,
+            baud,
+            output: output.ok_or("--output is required")?,
+            duration,
+            lines,
+        })
+    }
+}
+
+/// Logs a device to a CSV file with the connection threads of the GUI, until the duration or
+/// the number of lines is reached, or Ctrl-C is pressed.
+pub fn run(options: HeadlessOptions) -> Result<(), String> {
+    if !options.port.starts_with(TCP_PREFIX) {
+        let available = serialport::available_ports().map_err(|e| e.to_string())?;
+        if !available.iter().any(|p| p.port_name == options.port) {
+            return Err(format!("serial port {} not found", options.port));
+        }
+    }
+
+    let devices_lock = Arc::new(RwLock::new(vec![PortInfo::new(options.port.clone())]));
+    let devices_thread_lock = devices_lock.clone();
+    thread::spawn(|| devices_thread(devices_thread_lock));
+    let print_lock = Arc::new(RwLock::new(vec![Print::Empty]));
+    let (connection, threads) = spawn_connection(&devices_lock, &print_lock);
+
+    // Ctrl-C ends the logging like the limits, a second one exits right away
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler_interrupted = interrupted.clone();
+    ctrlc::set_handler(move || {
+        if handler_interrupted.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    })
+    .map_err(|e| e.to_string())?;
+
+    // the saved settings of the port (data bits, parity, ..) are used if there are any
+    let mut device = load_serial_settings()
+        .devices
+        .into_iter()
+        .find(|d| d.name == options.port)
+        .unwrap_or_default();
+    device.name = options.port.clone();
+    if let Some(baud) = options.baud {
+        device.baud_rate = baud;
+    }
+    if let Ok(mut write_guard) = connection.device_lock.write() {
+        *write_guard = device;
+    }
+    connection
+        .record_options_tx
+        .send(RecordOptions {
+            enable: true,
+            record_path: options.output.clone(),
+            ..RecordOptions::default()
+        })
+        .map_err(|e| e.to_string())?;
+
+    let start = Instant::now();
+    loop {
+        thread::sleep(Duration::from_millis(100));
+
+        // the printed messages are removed, the console keeps a limited number of lines
+        if let Ok(mut prints) = print_lock.write() {
+            for print in prints.drain(..) {
+                match print {
+                    Print::Message(s) => println!("[MSG] {s}"),
+                    Print::Error(s) => eprintln!("[ERR] {s}"),
+                    Print::Ok(s) => println!("[OK] {s}"),
+                    Print::Debug(_) | Print::Empty => {}
+                }
+            }
+        }
+
+        // the serial thread clears the name if the port could not be opened or was closed
+        let connected = connection
+            .device_lock
+            .read()
+            .map(|d| !d.name.is_empty())
+            .unwrap_or(false);
+        if !connected {
+            threads.stop();
+            return Err(format!("lost the connection to {}", options.port));
+        }
+
+        let samples = connection
+            .data_lock
+            .read()
+            .map(|d| d.total_samples)
+            .unwrap_or(0);
+        let done = interrupted.load(Ordering::Relaxed)
+            || options.duration.is_some_and(|d| start.elapsed() >= d)
+            || options.lines.is_some_and(|l| samples >= l);
+        if done {
+            // the record thread writes the last rows before it stops
+            threads.stop();
+            println!("[OK] logged {samples} samples to {:?}", options.output);
+            return Ok(());
+        }
+    }
+}
@@ -1,69 +1,562 @@
+use std::collections::VecDeque;
 use std::error::Error;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
-use csv::WriterBuilder;
+use chrono::{DateTime, Local};
+use csv::{ReaderBuilder, WriterBuilder};
+use eframe::egui::ColorImage;
+use serde::{Deserialize, Serialize};
 
-use crate::DataContainer;
+use crate::{DataContainer, Packet};
 
-/// A set of options for saving data to a CSV file.
-#[derive(Debug)]
+/// name of the optional column with the sample numbers
+const INDEX_COLUMN: &str = "index";
+
+/// The file formats the plot data can be saved in.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum FileFormat {
+    #[default]
+    Csv,
+    /// one JSON object with the names, times and values
+    Json,
+    /// newline-delimited JSON, one `{"t": .., "values": [..]}` object per sample
+    NdJson,
+}
+
+impl FileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Csv => "csv",
+            FileFormat::Json => "json",
+            FileFormat::NdJson => "ndjson",
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileFormat::Csv => write!(f, "CSV"),
+            FileFormat::Json => write!(f, "JSON"),
+            FileFormat::NdJson => write!(f, "NDJSON"),
+        }
+    }
+}
+
+/// What happens if the file to save already exists.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum OverwritePolicy {
+    Overwrite,
+    /// save to a new file with a numbered suffix (`data_001.csv`)
+    RenameIfExists,
+    /// ask in the GUI, files saved without asking are renamed
+    #[default]
+    Prompt,
+}
+
+impl fmt::Display for OverwritePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OverwritePolicy::Overwrite => write!(f, "Overwrite"),
+            OverwritePolicy::RenameIfExists => write!(f, "Rename"),
+            OverwritePolicy::Prompt => write!(f, "Ask"),
+        }
+    }
+}
+
+/// A set of options for saving data to a file.
+#[derive(Debug, Clone)]
 pub struct FileOptions {
     pub file_path: PathBuf,
+    pub format: FileFormat,
     pub save_absolute_time: bool,
     pub save_raw_traffic: bool,
+    /// append the rows added since the last save instead of overwriting the file
+    pub append: bool,
+    /// only save the samples whose time (in ms) is within this range, not used when appending
+    pub time_range: Option<(u128, u128)>,
+    /// decimal places of the values in a CSV file, `None` writes them at full precision
+    pub precision: Option<usize>,
+    /// write `#` comment lines with the port, capture start and sample count before the header
+    pub include_metadata: bool,
+    /// the port and baud rate for the metadata, e.g. `/dev/ttyUSB0 @ 115200 baud`
+    pub port: String,
+    /// write a leading `index` column with the number of each sample (0, 1, 2, ..)
+    pub include_index: bool,
+    pub overwrite: OverwritePolicy,
+}
+
+impl FileOptions {
+    /// The path the file is written to: `file_path`, or the first free numbered variant of
+    /// it if the file exists and may not be overwritten.
+    pub fn target_path(&self) -> PathBuf {
+        let appending = self.append && self.format == FileFormat::Csv;
+        if appending || self.overwrite == OverwritePolicy::Overwrite || !self.file_path.exists() {
+            return self.file_path.clone();
+        }
+        let stem = self
+            .file_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("data")
+            .to_string();
+        (1..)
+            .map(|i| {
+                let mut path = self.file_path.with_file_name(format!("{stem}_{i:03}"));
+                if let Some(extension) = self.file_path.extension() {
+                    path.set_extension(extension);
+                }
+                path
+            })
+            .find(|path| !path.exists())
+            .unwrap_or_else(|| self.file_path.clone())
+    }
+}
+
+/// Formats a value for a CSV file, a precision of zero writes integers.
+fn format_value(value: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => format!("{value:.precision$}"),
+        None => value.to_string(),
+    }
+}
+
+/// Remembers which rows have already been appended to a CSV file.
+#[derive(Debug)]
+pub struct AppendState {
+    file_path: PathBuf,
+    /// index (counted in `DataContainer::total_samples`) of the next row to be written
+    next_row: usize,
+    /// index (counted in `DataContainer::total_raw_packets`) of the next packet of the raw
+    /// traffic file, the raw traffic is trimmed independently of the rows
+    next_raw: usize,
+    columns: usize,
+    /// the file starts with an `index` column
+    index: bool,
+}
+
+/// Periodic CSV snapshots, an interval of zero disables them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AutoSaveOptions {
+    /// interval in seconds
+    pub interval: u64,
+    /// the snapshots are written here, the current directory is used if empty
+    pub directory: PathBuf,
+}
+
+impl AutoSaveOptions {
+    /// A new file name with the port and a timestamp, such that no snapshot overwrites the
+    /// previous one or the one of another connection. A snapshot within the same second is
+    /// numbered by `OverwritePolicy::RenameIfExists`.
+    pub fn next_file_path(&self, port: &str) -> PathBuf {
+        let directory = if self.directory.as_os_str().is_empty() {
+            std::env::current_dir().unwrap_or_default()
+        } else {
+            self.directory.clone()
+        };
+        // e.g. `/dev/ttyUSB0` -> `ttyUSB0_`, `tcp://host:4000` -> `host_4000_`
+        let port: String = port
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let port = if port.is_empty() { port } else { port + "_" };
+        directory.join(format!(
+            "autosave_{port}{}.csv",
+            Local::now().format("%Y%m%d_%H%M%S")
+        ))
+    }
+}
+
+/// A set of options for saving an image of the plot.
+#[derive(Debug)]
+pub struct ImageOptions {
+    pub file_path: PathBuf,
+}
+
+pub fn save_image(image: &ColorImage, image_options: &ImageOptions) -> Result<(), Box<dyn Error>> {
+    let mut path = image_options.file_path.clone();
+    path.set_extension("png");
+    image::save_buffer(
+        &path,
+        image.as_raw(),
+        image.width() as u32,
+        image.height() as u32,
+        image::ColorType::Rgba8,
+    )?;
+    Ok(())
+}
+
+/// Writes the metadata block of a CSV file, one `# key: value` comment line per entry.
+fn write_metadata(
+    writer: &mut impl Write,
+    data: &DataContainer,
+    csv_options: &FileOptions,
+) -> Result<(), Box<dyn Error>> {
+    let port = if csv_options.port.is_empty() {
+        "not connected"
+    } else {
+        csv_options.port.as_str()
+    };
+    writeln!(writer, "# port: {port}")?;
+    if let Some(start) = data
+        .absolute_time
+        .front()
+        .and_then(|&t| DateTime::from_timestamp_millis(t as i64))
+    {
+        writeln!(
+            writer,
+            "# capture start: {}",
+            start.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S%.3f")
+        )?;
+    }
+    writeln!(writer, "# samples: {}", data.time.len())?;
+    Ok(())
+}
+
+/// The column names of a CSV file: the optional index, the time and the series.
+fn csv_header(data: &DataContainer, csv_options: &FileOptions) -> Vec<String> {
+    let mut header = vec![];
+    if csv_options.include_index {
+        header.push(INDEX_COLUMN.to_string());
+    }
+    header.push("Time [ms]".to_string());
+    header.extend_from_slice(&data.names);
+    header
+}
+
+/// The fields of row `j` of the buffer.
+fn csv_row(data: &DataContainer, csv_options: &FileOptions, j: usize) -> Vec<String> {
+    let mut row = vec![];
+    if csv_options.include_index {
+        // the number of the sample, it continues across appends and a trimmed buffer
+        let first_sample = data.total_samples.saturating_sub(data.time.len());
+        row.push((first_sample + j).to_string());
+    }
+    row.push(if csv_options.save_absolute_time {
+        data.absolute_time[j].to_string()
+    } else {
+        data.time[j].to_string()
+    });
+    for value in data.dataset.iter() {
+        row.push(format_value(value[j], csv_options.precision));
+    }
+    row
 }
 
 pub fn save_to_csv(data: &DataContainer, csv_options: &FileOptions) -> Result<(), Box<dyn Error>> {
-    let mut wtr = WriterBuilder::new()
-        .has_headers(false)
-        .from_path(&csv_options.file_path)?;
+    let mut file = BufWriter::new(File::create(&csv_options.file_path)?);
+    if csv_options.include_metadata {
+        write_metadata(&mut file, data, csv_options)?;
+    }
+    let mut wtr = WriterBuilder::new().has_headers(false).from_writer(file);
     // serialize does not work, so we do it with a loop..
-    let mut header = vec!["Time [ms]".to_string()];
-    header.extend_from_slice(&data.names);
-    wtr.write_record(header)?;
+    wtr.write_record(csv_header(data, csv_options))?;
     for j in 0..data.dataset[0].len() {
-        let time = if csv_options.save_absolute_time {
-            data.absolute_time[j].to_string()
-        } else {
-            data.time[j].to_string()
-        };
-        let mut data_to_write = vec![time];
-        for value in data.dataset.iter() {
-            data_to_write.push(value[j].to_string());
+        wtr.write_record(csv_row(data, csv_options, j))?;
+    }
+    wtr.flush()?;
+    if csv_options.save_raw_traffic {
+        save_raw(data, &raw_traffic_path(&csv_options.file_path))?
+    }
+    Ok(())
+}
+
+/// The CSV file next to a data file which holds the raw traffic, `data.json` -> `dataraw.csv`.
+fn raw_traffic_path(file_path: &Path) -> PathBuf {
+    let stem = file_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("data");
+    file_path.with_file_name(format!("{stem}raw.csv"))
+}
+
+/// Appends the rows which have been added since the previous call for the same file.
+/// The header is only written to a new (empty) file.
+pub fn append_to_csv(
+    data: &DataContainer,
+    csv_options: &FileOptions,
+    state: &mut Option<AppendState>,
+) -> Result<usize, Box<dyn Error>> {
+    let columns = data.dataset.len();
+    let file_is_empty = std::fs::metadata(&csv_options.file_path)
+        .map(|m| m.len() == 0)
+        .unwrap_or(true);
+    if state.as_ref().map(|s| &s.file_path) != Some(&csv_options.file_path) {
+        // a file which existed before is continued from the data currently in the buffer
+        let mut file_columns = columns;
+        let mut file_index = csv_options.include_index;
+        if !file_is_empty {
+            let mut rdr = ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .comment(Some(b'#'))
+                .from_path(&csv_options.file_path)?;
+            if let Some(record) = rdr.records().next() {
+                let record = record?;
+                file_index = record.get(0).map(str::trim) == Some(INDEX_COLUMN);
+                file_columns = record.len().saturating_sub(1 + file_index as usize);
+            }
         }
-        wtr.write_record(&data_to_write)?;
+        *state = Some(AppendState {
+            file_path: csv_options.file_path.clone(),
+            next_row: data.total_samples.saturating_sub(data.time.len()),
+            next_raw: data
+                .total_raw_packets
+                .saturating_sub(data.raw_traffic.len()),
+            columns: file_columns,
+            index: file_index,
+        });
+    }
+    let state = state.as_mut().unwrap();
+    if state.columns != columns {
+        return Err(format!(
+            "the data has {} columns but the file has {}, not appending to it",
+            columns, state.columns
+        )
+        .into());
+    }
+    if state.index != csv_options.include_index {
+        let column = if state.index { "an" } else { "no" };
+        return Err(format!("the file has {column} index column, not appending to it").into());
+    }
+
+    let first_row = data.total_samples.saturating_sub(data.time.len());
+    // the data has been cleared since the last save
+    if state.next_row > data.total_samples {
+        state.next_row = first_row;
+    }
+    let start = state.next_row.max(first_row) - first_row;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&csv_options.file_path)?;
+    let mut wtr = WriterBuilder::new().has_headers(false).from_writer(file);
+    if file_is_empty {
+        wtr.write_record(csv_header(data, csv_options))?;
+    }
+    for j in start..data.time.len() {
+        wtr.write_record(csv_row(data, csv_options, j))?;
     }
     wtr.flush()?;
     if csv_options.save_raw_traffic {
-        let mut path = csv_options.file_path.clone();
-        let mut file_name = path
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string()
-            .replace(".csv", "");
-        file_name += "raw.csv";
-        path.set_file_name(file_name);
-        save_raw(data, &path)?
+        // the packets added since the last save are appended to their own file
+        let first_raw = data
+            .total_raw_packets
+            .saturating_sub(data.raw_traffic.len());
+        if state.next_raw > data.total_raw_packets {
+            state.next_raw = first_raw;
+        }
+        let start_raw = state.next_raw.max(first_raw) - first_raw;
+        let raw_path = raw_traffic_path(&csv_options.file_path);
+        let raw_is_empty = std::fs::metadata(&raw_path)
+            .map(|m| m.len() == 0)
+            .unwrap_or(true);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&raw_path)?;
+        let mut wtr = WriterBuilder::new().has_headers(false).from_writer(file);
+        if raw_is_empty {
+            wtr.write_record(raw_header())?;
+        }
+        for packet in &data.raw_traffic[start_raw..] {
+            wtr.write_record(raw_row(packet))?;
+        }
+        wtr.flush()?;
+        state.next_raw = data.total_raw_packets;
+    }
+    state.next_row = data.total_samples;
+    Ok(data.time.len() - start)
+}
+
+#[derive(Serialize)]
+struct JsonData<'a> {
+    names: &'a [String],
+    time: &'a VecDeque<u128>,
+    absolute_time: &'a VecDeque<u128>,
+    dataset: &'a [VecDeque<f64>],
+}
+
+#[derive(Serialize)]
+struct JsonSample {
+    t: u128,
+    values: Vec<f64>,
+}
+
+pub fn save_to_json(
+    data: &DataContainer,
+    json_options: &FileOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(&json_options.file_path)?);
+    serde_json::to_writer(
+        &mut writer,
+        &JsonData {
+            names: &data.names,
+            time: &data.time,
+            absolute_time: &data.absolute_time,
+            dataset: &data.dataset,
+        },
+    )?;
+    writer.flush()?;
+    if json_options.save_raw_traffic {
+        save_raw(data, &raw_traffic_path(&json_options.file_path))?
     }
     Ok(())
 }
 
-pub fn save_raw(data: &DataContainer, path: &PathBuf) -> Result<(), Box<dyn Error>> {
-    let mut wtr = WriterBuilder::new().has_headers(false).from_path(path)?;
-    let header = vec![
+pub fn save_to_ndjson(
+    data: &DataContainer,
+    json_options: &FileOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(&json_options.file_path)?);
+    for j in 0..data.time.len() {
+        let sample = JsonSample {
+            t: if json_options.save_absolute_time {
+                data.absolute_time[j]
+            } else {
+                data.time[j]
+            },
+            values: data.dataset.iter().map(|set| set[j]).collect(),
+        };
+        serde_json::to_writer(&mut writer, &sample)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    if json_options.save_raw_traffic {
+        save_raw(data, &raw_traffic_path(&json_options.file_path))?
+    }
+    Ok(())
+}
+
+/// The column names of a raw traffic file.
+fn raw_header() -> Vec<String> {
+    vec![
         "Time [ms]".to_string(),
         "Abs Time [ms]".to_string(),
         "Raw Traffic".to_string(),
-    ];
-    wtr.write_record(header)?;
+    ]
+}
 
-    for j in 0..data.dataset[0].len() {
-        let mut data_to_write = vec![data.time[j].to_string(), data.absolute_time[j].to_string()];
-        data_to_write.push(data.raw_traffic[j].payload.clone());
-        wtr.write_record(&data_to_write)?;
+/// The fields of a packet of the raw traffic.
+fn raw_row(packet: &Packet) -> Vec<String> {
+    vec![
+        packet.relative_time.to_string(),
+        packet.absolute_time.to_string(),
+        packet.payload.clone(),
+    ]
+}
+
+pub fn save_raw(data: &DataContainer, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let mut wtr = WriterBuilder::new().has_headers(false).from_path(path)?;
+    wtr.write_record(raw_header())?;
+
+    for packet in &data.raw_traffic {
+        wtr.write_record(raw_row(packet))?;
     }
     wtr.flush()?;
     Ok(())
 }
+
+/// Loads a file written by `save_to_csv` or by the recorder. The header line and the time
+/// column are optional, without a time column the line number is used as time. Comment lines
+/// (the metadata block) are skipped.
+pub fn load_from_csv(path: &Path) -> Result<DataContainer, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .from_path(path)?;
+    let mut records = vec![];
+    for record in rdr.records() {
+        let record = record?;
+        if !record.iter().all(|field| field.trim().is_empty()) {
+            records.push(record);
+        }
+    }
+    if records.is_empty() {
+        return Err("file is empty".into());
+    }
+
+    let is_numeric = |record: &csv::StringRecord| {
+        record
+            .iter()
+            .all(|field| field.trim().parse::<f64>().is_ok())
+    };
+    let header: Option<Vec<String>> = if is_numeric(&records[0]) {
+        None
+    } else {
+        Some(
+            records
+                .remove(0)
+                .iter()
+                .map(|f| f.trim().to_string())
+                .collect(),
+        )
+    };
+    // the sample numbers of an index column are not loaded
+    let has_index_column =
+        header.as_ref().and_then(|h| h.first()).map(String::as_str) == Some(INDEX_COLUMN);
+    let time_column = has_index_column as usize;
+    let has_time_column = header
+        .as_ref()
+        .and_then(|h| h.get(time_column))
+        .map(|name| name.starts_with("Time") || name == "Timestamp")
+        .unwrap_or(false);
+    let first_column = time_column + has_time_column as usize;
+
+    let number_of_columns = records[0].len().saturating_sub(first_column).max(1);
+    let mut data = DataContainer {
+        time: VecDeque::new(),
+        names: match &header {
+            Some(h) => h.iter().skip(first_column).cloned().collect(),
+            None => (0..number_of_columns)
+                .map(|i| format!("Column {i}"))
+                .collect(),
+        },
+        absolute_time: VecDeque::new(),
+        dataset: vec![VecDeque::new(); number_of_columns],
+        raw_traffic: vec![],
+        header: None,
+        total_samples: records.len(),
+        ..Default::default()
+    };
+    data.names.resize_with(number_of_columns, || "".to_string());
+
+    for (i, record) in records.iter().enumerate() {
+        if record.len() != number_of_columns + first_column {
+            return Err(format!("line {} has {} columns", i + 1, record.len()).into());
+        }
+        let time = if has_time_column {
+            record[time_column].trim().parse::<f64>()? as u128
+        } else {
+            i as u128
+        };
+        data.time.push_back(time);
+        data.absolute_time.push_back(time);
+        for (set, field) in data
+            .dataset
+            .iter_mut()
+            .zip(record.iter().skip(first_column))
+        {
+            set.push_back(field.trim().parse::<f64>()?);
+        }
+    }
+
+    // epoch timestamps (absolute time) are shown relative to the first sample
+    if let Some(&t_zero) = data.time.front() {
+        if t_zero > 1_000_000_000_000 {
+            for t in data.time.iter_mut() {
+                *t = t.saturating_sub(t_zero);
+            }
+        }
+    }
+    Ok(data)
+}
@@ -5,26 +5,48 @@ extern crate csv;
 extern crate preferences;
 extern crate serde;
 
+use std::borrow::Cow;
 use std::cmp::max;
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{mpsc, Arc, RwLock};
-use std::thread;
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use eframe::egui::{vec2, ViewportBuilder, Visuals};
+use crossbeam_channel::{unbounded, Receiver, Select, Sender, TryRecvError};
+use eframe::egui::{vec2, ViewportBuilder};
 use eframe::{egui, icon_data};
-use gui::{PlotOptions, RawTrafficOptions};
+use gui::{AlarmOptions, PlotOptions, RawTrafficOptions, TrafficCap, TriggerEdge, TriggerOptions};
 use preferences::AppInfo;
+use regex::Regex;
+use rodio::source::{SineWave, Source};
 
-use crate::data::{DataContainer, Packet};
+use crate::computed::{evaluate, ComputedColumn};
+use crate::data::{DataContainer, Marker, Packet, SerialDirection};
 use crate::gui::{load_gui_settings, print_to_console, MyApp, Print, RIGHT_PANEL_WIDTH};
-use crate::io::{save_to_csv, FileOptions};
+use crate::headless::HeadlessOptions;
+use crate::io::{
+    append_to_csv, save_to_csv, save_to_json, save_to_ndjson, AppendState, AutoSaveOptions,
+    FileFormat, FileOptions, OverwritePolicy,
+};
+use crate::parser::{
+    extract_named, split, split_header, strip_ansi, DecimalSeparator, Delimiter, MissingFields,
+    ParsingOptions,
+};
 use crate::record::{record_thread, RecordData, RecordOptions};
-use crate::serial::{load_serial_settings, serial_thread, Device};
+use crate::serial::{
+    devices_thread, load_serial_settings, serial_thread, ByteLog, ConnectionStatus, Device,
+    PortControl, PortInfo, SendPayload,
+};
 
+mod computed;
 mod data;
+mod fft;
+mod frame;
 mod gui;
+mod headless;
 mod io;
+mod parser;
 mod record;
 mod serial;
 mod toggle;
@@ -35,25 +57,306 @@ const APP_INFO: AppInfo = AppInfo {
 };
 const PREFS_KEY: &str = "config/gui";
 const PREFS_KEY_SERIAL: &str = "config/serial_devices";
+const PREFS_KEY_PROFILES: &str = "config/profiles";
+/// upper bound for how long the main thread sleeps without any events or packets
+const IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+/// number of waiting packets above which a growing backlog is reported
+const BACKLOG_WARNING: usize = 1000;
+/// maximum number of packets parsed under one write lock, so that the GUI events and the
+/// readers of the data are not held up by a long backlog
+const MAX_PACKET_BATCH: usize = 500;
+/// minimum time between two alarms of the same series, against noise around a limit
+const ALARM_HOLDOFF: Duration = Duration::from_secs(5);
+/// number of the latest received lines the parse quality is calculated over
+const PARSE_QUALITY_WINDOW: usize = 500;
 
 enum GuiEvent {
     SetRawTrafficOptions(RawTrafficOptions),
     SetBufferSize(usize),
+    SetDelimiter(Delimiter),
+    SetResetLimits(usize, usize),
+    SetRegex(Option<Regex>),
+    SetParseHex(bool),
+    SetDecimalSeparator(DecimalSeparator),
+    SetMissingFields(MissingFields),
+    SetComputed(Vec<ComputedColumn>),
+    SetDeviceTime(bool, usize),
+    SetTrigger(TriggerOptions),
+    SetAlarms(AlarmOptions),
+    ArmTrigger,
+    SetHeaderMode(bool),
     SetNames(Vec<String>),
     SaveCSV(FileOptions),
+    SetAutoSave(AutoSaveOptions),
+    SetSaveAbsoluteTime(bool),
+    /// the time without received data after which an error is raised, `None` disables it
+    SetWatchdog(Option<Duration>),
+    LoadData(DataContainer),
     Clear,
 }
 
-fn split(payload: &str) -> Vec<f64> {
-    let mut split_data: Vec<&str> = vec![];
-    for s in payload.split(':') {
-        split_data.extend(s.split(','));
+/// Whether the latest received lines were parsed into samples, over a rolling window.
+#[derive(Default)]
+struct ParseQuality {
+    results: VecDeque<bool>,
+    parsed: usize,
+}
+
+impl ParseQuality {
+    /// Adds the result of a line and returns the share of the parsed lines in the window.
+    fn push(&mut self, parsed: bool) -> f64 {
+        self.results.push_back(parsed);
+        self.parsed += parsed as usize;
+        if self.results.len() > PARSE_QUALITY_WINDOW {
+            if let Some(true) = self.results.pop_front() {
+                self.parsed -= 1;
+            }
+        }
+        self.parsed as f64 / self.results.len() as f64
+    }
+}
+
+fn trim_buffer(data: &mut DataContainer, buffer_size: usize) {
+    // drop the oldest samples, popping from the front of a VecDeque is O(1)
+    for set in data.dataset.iter_mut() {
+        while set.len() > buffer_size {
+            set.pop_front();
+        }
+    }
+    while data.time.len() > buffer_size {
+        data.time.pop_front();
+    }
+    while data.absolute_time.len() > buffer_size {
+        data.absolute_time.pop_front();
+    }
+    if let Some(first) = data.time.front().copied() {
+        while data
+            .markers
+            .front()
+            .is_some_and(|m| m.relative_time < first)
+        {
+            data.markers.pop_front();
+        }
+    }
+}
+
+/// Plays a short tone on the default audio output. The sound is played in its own thread, as
+/// the output stream has to be kept until it ends.
+fn beep() {
+    thread::spawn(|| {
+        let Ok((_stream, handle)) = rodio::OutputStream::try_default() else {
+            return;
+        };
+        let Ok(sink) = rodio::Sink::try_new(&handle) else {
+            return;
+        };
+        sink.append(
+            SineWave::new(880.0)
+                .take_duration(Duration::from_millis(200))
+                .amplify(0.2),
+        );
+        sink.sleep_until_end();
+    });
+}
+
+/// Raises an alarm for every series whose newest sample left its limits. A series has to
+/// come back inside its limits and wait `ALARM_HOLDOFF` before it raises the next alarm, a
+/// violation which lasts beyond the hold-off is raised then.
+fn check_alarms(
+    data: &mut DataContainer,
+    alarm_options: &AlarmOptions,
+    last_alarms: &mut Vec<Option<Instant>>,
+    print_lock: &Arc<RwLock<Vec<Print>>>,
+) {
+    data.alarms_active.resize(data.dataset.len(), false);
+    last_alarms.resize(data.dataset.len(), None);
+    for (i, set) in data.dataset.iter().enumerate() {
+        let (Some(limits), Some(value)) = (alarm_options.limits.get(i), set.back()) else {
+            continue;
+        };
+        let violated = limits.enable && (*value < limits.low || *value > limits.high);
+        if violated
+            && !data.alarms_active[i]
+            && !last_alarms[i].is_some_and(|t| t.elapsed() < ALARM_HOLDOFF)
+        {
+            print_to_console(
+                print_lock,
+                Print::Error(format!(
+                    "ALARM: {} = {value} is outside of [{}, {}]",
+                    data.names.get(i).map_or("", |n| n.as_str()),
+                    limits.low,
+                    limits.high
+                )),
+            );
+            if alarm_options.beep {
+                beep();
+            }
+            last_alarms[i] = Some(Instant::now());
+            data.alarms_active[i] = true;
+        } else if !violated {
+            data.alarms_active[i] = false;
+        }
+    }
+}
+
+/// Replaces the computed columns at the end of the dataset and calculates them for all samples
+/// in the buffer.
+fn recompute_columns(data: &mut DataContainer, columns: &[ComputedColumn]) {
+    let raw_columns = data.dataset.len().saturating_sub(data.computed_columns);
+    data.dataset.truncate(raw_columns);
+    data.names.truncate(raw_columns);
+    let mut computed = vec![VecDeque::with_capacity(data.time.len()); columns.len()];
+    for i in 0..data.time.len() {
+        let values: Vec<f64> = data
+            .dataset
+            .iter()
+            .map(|set| set.get(i).copied().unwrap_or(f64::NAN))
+            .collect();
+        for (set, value) in computed
+            .iter_mut()
+            .zip(evaluate(columns, &values, &data.names))
+        {
+            set.push_back(value);
+        }
+    }
+    data.dataset.extend(computed);
+    data.names
+        .extend(columns.iter().map(|column| column.column_name()));
+    data.computed_columns = columns.len();
+    if data.header.is_some() {
+        data.header = Some(data.names.clone());
+    }
+}
+
+/// Drops the oldest packets of the raw traffic beyond the count or the size limit.
+fn trim_raw_traffic(data: &mut DataContainer, options: &RawTrafficOptions) {
+    let excess = match options.cap {
+        TrafficCap::Count => data.raw_traffic.len().saturating_sub(options.max_len),
+        TrafficCap::Bytes => {
+            let limit = options.max_kb * 1024;
+            let mut bytes = data.raw_traffic_bytes;
+            let mut excess = 0;
+            for packet in &data.raw_traffic {
+                if bytes <= limit {
+                    break;
+                }
+                bytes -= packet.payload.len();
+                excess += 1;
+            }
+            excess
+        }
+    };
+    if excess > 0 {
+        for packet in data.raw_traffic.drain(..excess) {
+            data.raw_traffic_bytes -= packet.payload.len();
+        }
+    }
+}
+
+/// Fires the trigger if the newest sample crossed the level and freezes the buffer once the
+/// samples after the trigger are complete. Returns true when the capture is complete.
+fn update_trigger(data: &mut DataContainer, trigger: &TriggerOptions) -> bool {
+    if data.trigger_sample.is_none() {
+        let Some(series) = data.dataset.get(trigger.series) else {
+            return false;
+        };
+        let len = series.len();
+        if len < 2 {
+            return false;
+        }
+        let (previous, current) = (series[len - 2], series[len - 1]);
+        let crossed = match trigger.edge {
+            TriggerEdge::Rising => previous < trigger.level && current >= trigger.level,
+            TriggerEdge::Falling => previous > trigger.level && current <= trigger.level,
+        };
+        if crossed {
+            data.trigger_sample = Some(data.total_samples - 1);
+        }
+    }
+    let Some(trigger_sample) = data.trigger_sample else {
+        return false;
+    };
+    if data.total_samples - 1 - trigger_sample < trigger.post_samples {
+        return false;
+    }
+    // only keep the window around the trigger (which might have left a small buffer already)
+    let start = data
+        .trigger_index()
+        .map_or(0, |idx| idx.saturating_sub(trigger.pre_samples));
+    for set in data.dataset.iter_mut() {
+        set.drain(..start);
+    }
+    data.time.drain(..start);
+    data.absolute_time.drain(..start);
+    data.trigger_captured = true;
+    true
+}
+
+fn save_csv(
+    data_lock: &Arc<RwLock<DataContainer>>,
+    print_lock: &Arc<RwLock<Vec<Print>>>,
+    csv_options: &FileOptions,
+    append_state: &mut Option<AppendState>,
+) {
+    if let Ok(read_guard) = data_lock.read() {
+        if csv_options.append && csv_options.format == FileFormat::Csv {
+            match append_to_csv(&read_guard, csv_options, append_state) {
+                Ok(rows) => {
+                    print_to_console(
+                        print_lock,
+                        Print::Ok(format!(
+                            "appended {} rows to {:?} ",
+                            rows, csv_options.file_path
+                        )),
+                    );
+                }
+                Err(e) => {
+                    print_to_console(
+                        print_lock,
+                        Print::Error(format!(
+                            "failed to append to {:?}: {}",
+                            csv_options.file_path, e
+                        )),
+                    );
+                }
+            }
+            return;
+        }
+        let csv_options = &FileOptions {
+            file_path: csv_options.target_path(),
+            ..csv_options.clone()
+        };
+        let sliced;
+        let data = match csv_options.time_range {
+            Some((start, end)) => {
+                sliced = read_guard.slice_time(start, end);
+                &sliced
+            }
+            None => &*read_guard,
+        };
+        let result = match csv_options.format {
+            FileFormat::Csv => save_to_csv(data, csv_options),
+            FileFormat::Json => save_to_json(data, csv_options),
+            FileFormat::NdJson => save_to_ndjson(data, csv_options),
+        };
+        match result {
+            Ok(_) => {
+                print_to_console(
+                    print_lock,
+                    Print::Ok(format!("saved data file to {:?} ", csv_options.file_path)),
+                );
+            }
+            Err(e) => {
+                print_to_console(
+                    print_lock,
+                    Print::Error(format!(
+                        "failed to save file to {:?}: {:?}",
+                        csv_options.file_path, e
+                    )),
+                );
+            }
+        }
     }
-    split_data
-        .iter()
-        .map(|x| x.trim())
-        .flat_map(|x| x.parse::<f64>())
-        .collect()
 }
 
 fn main_thread(
@@ -62,14 +365,68 @@ fn main_thread(
     raw_data_rx: Receiver<Packet>,
     gui_event_rx: Receiver<GuiEvent>,
     record_data_tx: Sender<RecordData>,
+    device_lock: Arc<RwLock<Device>>,
+    raw_recording: Arc<AtomicBool>,
+    connected_lock: Arc<RwLock<ConnectionStatus>>,
 ) {
     // reads data from mutex, samples and saves if needed
     // let mut data = DataContainer::default();
     let mut raw_traffic_options = RawTrafficOptions::default();
-    let mut failed_format_counter = 0;
+    // consecutive lines with a different number of values than the dataset
+    let mut mismatch_counter = 0;
+    // consecutive mismatching lines with the same (new) number of values
+    let mut new_shape_counter = 0;
+    let mut new_shape_len = 0;
+    // the names before the last reset to a different column count
+    let mut previous_names: Vec<String> = vec![];
+    let mut dropped_lines: usize = 0;
+    // dropped lines are reported once per second, not one message per line
+    let mut reported_dropped_lines: usize = 0;
+    // arrival time of the latest sample, to place the markers between device timestamps
+    let mut last_sample_arrival: Option<u128> = None;
+    let mut parse_quality = ParseQuality::default();
+    let mut watchdog: Option<Duration> = None;
+    let mut last_received = Instant::now();
+    // the start of the connection the watchdog is timing
+    let mut watched_since: Option<Instant> = None;
     let mut buffer_size = PlotOptions::default().buffer_size;
+    let mut parsing_options = ParsingOptions::default();
+    let mut trigger = TriggerOptions::default();
+    let mut alarm_options = AlarmOptions::default();
+    // when each series raised its last alarm
+    let mut last_alarms: Vec<Option<Instant>> = vec![];
+    let mut header_mode = PlotOptions::default().header_mode;
+    let mut pending_header: Option<Vec<String>> = None;
+    let mut auto_save_options = AutoSaveOptions::default();
+    let mut save_absolute_time = false;
+    let mut last_auto_save = Instant::now();
+    let mut append_state: Option<AppendState> = None;
+    // samples appended since `rate_start`, the rate is updated once per second
+    let mut rate_counter: usize = 0;
+    let mut bytes_counter: usize = 0;
+    let mut rate_start = Instant::now();
+    let mut last_backlog: usize = 0;
+    let mut lagging = false;
     loop {
-        if let Ok(event) = gui_event_rx.try_recv() {
+        // sleep until an event or a packet arrives, or the next auto-save is due
+        let timeout = if auto_save_options.interval > 0 {
+            Duration::from_secs(auto_save_options.interval).saturating_sub(last_auto_save.elapsed())
+        } else {
+            IDLE_TIMEOUT
+        };
+        let mut select = Select::new();
+        select.recv(&gui_event_rx);
+        select.recv(&raw_data_rx);
+        let _ = select.ready_timeout(timeout);
+
+        // events are still handled before the next packet
+        let event = match gui_event_rx.try_recv() {
+            Ok(event) => Some(event),
+            // the gui is gone, nothing left to do
+            Err(TryRecvError::Disconnected) => break,
+            Err(TryRecvError::Empty) => None,
+        };
+        if let Some(event) = event {
             match event {
                 GuiEvent::SetRawTrafficOptions(opt) => raw_traffic_options = opt,
                 GuiEvent::SetNames(names) => {
@@ -78,164 +435,626 @@ fn main_thread(
                     }
                 }
                 GuiEvent::SaveCSV(csv_options) => {
-                    if let Ok(read_guard) = data_lock.read() {
-                        match save_to_csv(&read_guard, &csv_options) {
-                            Ok(_) => {
-                                print_to_console(
-                                    &print_lock,
-                                    Print::Ok(format!(
-                                        "saved data file to {:?} ",
-                                        csv_options.file_path
-                                    )),
-                                );
-                            }
-                            Err(e) => {
-                                print_to_console(
-                                    &print_lock,
-                                    Print::Error(format!(
-                                        "failed to save file to {:?}: {:?}",
-                                        csv_options.file_path, e
-                                    )),
-                                );
-                            }
-                        }
+                    save_csv(&data_lock, &print_lock, &csv_options, &mut append_state);
+                }
+                GuiEvent::LoadData(loaded_data) => {
+                    // the packets still waiting belong to the replaced live data
+                    while raw_data_rx.try_recv().is_ok() {}
+                    if let Ok(mut write_guard) = data_lock.write() {
+                        *write_guard = loaded_data;
+                        mismatch_counter = 0;
+                        new_shape_counter = 0;
                     }
                 }
                 GuiEvent::Clear => {
                     if let Ok(mut write_guard) = data_lock.write() {
                         *write_guard = DataContainer::default();
-                        failed_format_counter = 0;
+                        mismatch_counter = 0;
+                        new_shape_counter = 0;
+                        bytes_counter = 0;
+                        parse_quality = ParseQuality::default();
+                    }
+                }
+                GuiEvent::SetDelimiter(d) => parsing_options.delimiter = d,
+                GuiEvent::SetRegex(regex) => parsing_options.regex = regex,
+                GuiEvent::SetParseHex(hex) => parsing_options.parse_hex = hex,
+                GuiEvent::SetDecimalSeparator(d) => parsing_options.decimal_separator = d,
+                GuiEvent::SetMissingFields(m) => parsing_options.missing_fields = m,
+                GuiEvent::SetComputed(columns) => {
+                    if let Ok(mut write_guard) = data_lock.write() {
+                        recompute_columns(&mut write_guard, &columns);
+                    }
+                    parsing_options.computed = columns;
+                }
+                GuiEvent::SetDeviceTime(enable, column) => {
+                    parsing_options.device_time = enable;
+                    parsing_options.time_column = column;
+                }
+                GuiEvent::SetAlarms(opt) => alarm_options = opt,
+                GuiEvent::SetTrigger(opt) => {
+                    trigger = opt;
+                    if let Ok(mut write_guard) = data_lock.write() {
+                        write_guard.trigger_sample = None;
+                        write_guard.trigger_captured = false;
+                    }
+                }
+                GuiEvent::ArmTrigger => {
+                    if let Ok(mut write_guard) = data_lock.write() {
+                        write_guard.trigger_sample = None;
+                        write_guard.trigger_captured = false;
+                    }
+                }
+                GuiEvent::SetResetLimits(mismatches, stable_lines) => {
+                    parsing_options.reset_after_mismatches = mismatches;
+                    parsing_options.reset_stable_lines = stable_lines;
+                }
+                GuiEvent::SetHeaderMode(h) => {
+                    header_mode = h;
+                    pending_header = None;
+                }
+                GuiEvent::SetAutoSave(opt) => {
+                    auto_save_options = opt;
+                    last_auto_save = Instant::now();
+                }
+                GuiEvent::SetSaveAbsoluteTime(a) => save_absolute_time = a,
+                GuiEvent::SetWatchdog(timeout) => watchdog = timeout,
+                GuiEvent::SetBufferSize(s) => {
+                    buffer_size = s;
+                    if let Ok(mut write_guard) = data_lock.write() {
+                        trim_buffer(&mut write_guard, buffer_size);
                     }
                 }
-                GuiEvent::SetBufferSize(s) => buffer_size = s,
             }
         }
 
-        if let Ok(packet) = raw_data_rx.recv_timeout(Duration::from_millis(1)) {
-            if !packet.payload.is_empty() {
-                if let Ok(write_guard) = data_lock.write() {
-                    let mut data = write_guard;
+        if auto_save_options.interval > 0
+            && last_auto_save.elapsed() >= Duration::from_secs(auto_save_options.interval)
+        {
+            last_auto_save = Instant::now();
+            let has_data = data_lock
+                .read()
+                .map(|d| !d.time.is_empty())
+                .unwrap_or(false);
+            if has_data {
+                // each connection saves its own snapshots, the port keeps them apart
+                let port = device_lock
+                    .read()
+                    .map(|d| d.name.clone())
+                    .unwrap_or_default();
+                save_csv(
+                    &data_lock,
+                    &print_lock,
+                    &FileOptions {
+                        file_path: auto_save_options.next_file_path(&port),
+                        format: FileFormat::Csv,
+                        save_absolute_time,
+                        save_raw_traffic: false,
+                        append: false,
+                        time_range: None,
+                        precision: None,
+                        include_metadata: false,
+                        port: "".to_string(),
+                        include_index: false,
+                        overwrite: OverwritePolicy::RenameIfExists,
+                    },
+                    &mut append_state,
+                );
+            }
+        }
+
+        // all waiting packets are parsed under one write lock instead of one lock per packet,
+        // which would contend with the GUI at high rates
+        let mut packets = vec![];
+        // the serial thread has stopped, all received packets are handled in this batch
+        let mut disconnected = false;
+        while packets.len() < MAX_PACKET_BATCH {
+            match raw_data_rx.try_recv() {
+                Ok(packet) => packets.push(packet),
+                Err(TryRecvError::Disconnected) => {
+                    disconnected = true;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        if !packets.is_empty() {
+            if let Ok(mut data) = data_lock.write() {
+                for mut packet in packets {
+                    if packet.payload.is_empty() {
+                        continue;
+                    }
+                    // the raw recording keeps the lines as received
+                    // only the recording needs the bytes, the rest works with the text
+                    let raw = std::mem::take(&mut packet.raw);
+                    // counted as received, before the escape sequences are stripped
+                    let byte_count = raw.len();
+                    if raw_recording.load(Ordering::Relaxed) {
+                        record_data_tx
+                            .send(RecordData::Raw(Packet {
+                                raw,
+                                ..packet.clone()
+                            }))
+                            .unwrap_or_default();
+                    }
+                    if raw_traffic_options.strip_ansi {
+                        if let Cow::Owned(stripped) = strip_ansi(&packet.payload) {
+                            packet.payload = stripped;
+                        }
+                    }
+                    if packet.direction == SerialDirection::Receive {
+                        data.received_bytes += byte_count;
+                        data.received_lines += 1;
+                        bytes_counter += byte_count;
+                        last_received = Instant::now();
+                        if data.stalled {
+                            data.stalled = false;
+                            print_to_console(
+                                &print_lock,
+                                Print::Ok("data is arriving again".to_string()),
+                            );
+                        }
+                    } else {
+                        // a command often switches the mode of the device, mark it on the plot.
+                        // With device timestamps it is placed after the latest sample by the
+                        // time since that arrived.
+                        let relative_time = match (data.time.back(), last_sample_arrival) {
+                            (Some(time), Some(arrival)) if parsing_options.device_time => {
+                                time + packet.relative_time.saturating_sub(arrival)
+                            }
+                            _ => packet.relative_time,
+                        };
+                        data.markers.push_back(Marker {
+                            relative_time,
+                            absolute_time: packet.absolute_time,
+                            label: packet.payload.trim().to_string(),
+                        });
+                        // without samples the markers are not trimmed by time
+                        while data.markers.len() > buffer_size {
+                            data.markers.pop_front();
+                        }
+                    }
                     if raw_traffic_options.enable {
+                        data.raw_traffic_bytes += packet.payload.len();
                         data.raw_traffic.push(packet.clone());
-                        let raw_traffic_len = data.raw_traffic.len();
-                        data.raw_traffic = data
-                            .raw_traffic
-                            .split_off(raw_traffic_len.saturating_sub(raw_traffic_options.max_len));
+                        data.total_raw_packets += 1;
+                        trim_raw_traffic(&mut data, &raw_traffic_options);
                     }
-                    let split_data = split(&packet.payload);
-                    if data.dataset.is_empty()
-                        || failed_format_counter > 10
-                        || data.dataset[0].len() != data.time.len()
+                    let (mut split_data, mut regex_names) =
+                        match (&packet.values, &parsing_options.regex) {
+                            // binary frames are decoded by the serial thread already
+                            (Some(values), _) => (values.clone(), None),
+                            // lines which do not match the regex have no values and are skipped
+                            (None, Some(regex)) => match extract_named(
+                                &packet.payload,
+                                regex,
+                                parsing_options.parse_hex,
+                                parsing_options.decimal_separator,
+                            ) {
+                                Some((names, values)) => (values, Some(names)),
+                                None => (vec![], None),
+                            },
+                            (None, None) => (
+                                split(
+                                    &packet.payload,
+                                    &parsing_options.delimiter,
+                                    parsing_options.parse_hex,
+                                    parsing_options.decimal_separator,
+                                ),
+                                None,
+                            ),
+                        };
+                    // the device supplies its own timestamp (in ms) in one of the columns
+                    let device_time = if parsing_options.device_time
+                        && parsing_options.time_column < split_data.len()
                     {
-                        // resetting dataset
-                        data.time = vec![];
-                        data.absolute_time = vec![];
-                        data.dataset = vec![vec![]; max(split_data.len(), 1)];
-                        if data.names.len() != split_data.len() {
-                            data.names = (0..max(split_data.len(), 1))
-                                .map(|i| format!("Column {i}"))
-                                .collect();
+                        if let Some(names) = regex_names.as_mut() {
+                            names.remove(parsing_options.time_column);
                         }
-                        failed_format_counter = 0;
-                        // println!("resetting dataset. split length = {}, length data.dataset = {}", split_data.len(), data.dataset.len());
-                    } else if split_data.len() == data.dataset.len() {
-                        record_data_tx
-                            .send(RecordData {
-                                time: packet.absolute_time,
-                                datas: split_data.clone(),
-                            })
-                            .unwrap_or_default();
-                        // appending data
-                        for (i, set) in data.dataset.iter_mut().enumerate() {
-                            set.push(split_data[i]);
-                            failed_format_counter = 0;
-                            while set.len() > buffer_size {
-                                set.remove(0);
+                        Some(split_data.remove(parsing_options.time_column).max(0.0) as u128)
+                    } else {
+                        None
+                    };
+                    // a sparse line is completed instead of being dropped as a mismatch
+                    let raw_columns = data.dataset.len().saturating_sub(data.computed_columns);
+                    if !split_data.is_empty()
+                        && split_data.len() < raw_columns
+                        && !data.time.is_empty()
+                    {
+                        match parsing_options.missing_fields {
+                            MissingFields::Strict => {}
+                            MissingFields::CarryForward => {
+                                let last_values = data.dataset[split_data.len()..raw_columns]
+                                    .iter()
+                                    .map(|set| set.back().copied().unwrap_or(f64::NAN))
+                                    .collect::<Vec<_>>();
+                                split_data.extend(last_values);
                             }
+                            MissingFields::NanFill => split_data.resize(raw_columns, f64::NAN),
+                        }
+                    }
+                    // the computed columns are appended to the parsed ones
+                    if !split_data.is_empty() && !parsing_options.computed.is_empty() {
+                        let raw_columns = data.names.len().saturating_sub(data.computed_columns);
+                        let computed = evaluate(
+                            &parsing_options.computed,
+                            &split_data,
+                            &data.names[..raw_columns],
+                        );
+                        split_data.extend(computed);
+                        if let Some(names) = regex_names.as_mut() {
+                            names.extend(parsing_options.computed.iter().map(|c| c.column_name()));
                         }
-                        data.time.push(packet.relative_time);
-                        while data.time.len() > buffer_size {
-                            data.time.remove(0);
+                    }
+                    // whether the line was parsed into a sample, `None` for headers and frozen captures
+                    let mut parsed = None;
+                    if header_mode
+                        && parsing_options.regex.is_none()
+                        && split_data.is_empty()
+                        && packet.direction == SerialDirection::Receive
+                    {
+                        // a line without any numbers might be a header, it is adopted
+                        // as soon as a numeric line with the same field count arrives
+                        let mut header = split_header(&packet.payload, &parsing_options.delimiter);
+                        if parsing_options.device_time && parsing_options.time_column < header.len()
+                        {
+                            header.remove(parsing_options.time_column);
                         }
-                        data.absolute_time.push(packet.absolute_time);
-                        while data.absolute_time.len() > buffer_size {
-                            data.absolute_time.remove(0);
+                        pending_header = Some(header);
+                    } else if split_data.is_empty() {
+                        // a line without any values (e.g. a log message), it is not plotted
+                        parsed = Some(false);
+                    } else if trigger.enable && data.trigger_captured {
+                        // the capture stays frozen until the trigger is re-armed, only the
+                        // shown data is frozen, the recording continues
+                        if split_data.len() == data.dataset.len() {
+                            record_data_tx
+                                .send(RecordData::Values {
+                                    time: packet.absolute_time,
+                                    datas: split_data.clone(),
+                                })
+                                .unwrap_or_default();
                         }
                     } else {
-                        // not same length
-                        failed_format_counter += 1;
-                        // println!("not same length in main! length split_data = {}, length data.dataset = {}", split_data.len(), data.dataset.len())
+                        let mut reset_dataset =
+                            data.dataset.is_empty() || data.dataset[0].len() != data.time.len();
+                        if !reset_dataset && split_data.len() != data.dataset.len() {
+                            if data.time.is_empty() {
+                                reset_dataset = true;
+                            } else {
+                                // not same length, the line is dropped. The dataset is only reset
+                                // if the mismatches continue and the new column count is stable
+                                if new_shape_counter > 0 && new_shape_len == split_data.len() {
+                                    new_shape_counter += 1;
+                                } else {
+                                    new_shape_len = split_data.len();
+                                    new_shape_counter = 1;
+                                }
+                                mismatch_counter += 1;
+                                dropped_lines += 1;
+                                reset_dataset = mismatch_counter
+                                    >= parsing_options.reset_after_mismatches
+                                    && new_shape_counter >= parsing_options.reset_stable_lines;
+                            }
+                        }
+
+                        if reset_dataset {
+                            if !data.time.is_empty() {
+                                print_to_console(
+                                    &print_lock,
+                                    Print::Debug(format!(
+                                        "resetting dataset from {} to {} columns",
+                                        data.dataset.len(),
+                                        split_data.len()
+                                    )),
+                                );
+                            }
+                            data.time = VecDeque::new();
+                            data.absolute_time = VecDeque::new();
+                            data.dataset = vec![VecDeque::new(); max(split_data.len(), 1)];
+                            // the names are kept if the column count is the same, or restored if
+                            // a short change of the shape (e.g. a glitch) returns to the old one
+                            if data.names.len() != split_data.len() {
+                                if previous_names.len() == split_data.len() {
+                                    std::mem::swap(&mut data.names, &mut previous_names);
+                                } else {
+                                    let raw_columns = split_data
+                                        .len()
+                                        .saturating_sub(parsing_options.computed.len());
+                                    previous_names = std::mem::replace(
+                                        &mut data.names,
+                                        (0..max(raw_columns, 1))
+                                            .map(|i| format!("Column {i}"))
+                                            .chain(
+                                                parsing_options
+                                                    .computed
+                                                    .iter()
+                                                    .map(|c| c.column_name()),
+                                            )
+                                            .collect(),
+                                    );
+                                }
+                            }
+                            data.computed_columns = parsing_options.computed.len();
+                            data.trigger_sample = None;
+                            mismatch_counter = 0;
+                            new_shape_counter = 0;
+                            // println!("resetting dataset. split length = {}, length data.dataset = {}", split_data.len(), data.dataset.len());
+                        } else if split_data.len() == data.dataset.len() {
+                            record_data_tx
+                                .send(RecordData::Values {
+                                    time: packet.absolute_time,
+                                    datas: split_data.clone(),
+                                })
+                                .unwrap_or_default();
+                            // appending data
+                            for (i, set) in data.dataset.iter_mut().enumerate() {
+                                set.push_back(split_data[i]);
+                            }
+                            mismatch_counter = 0;
+                            new_shape_counter = 0;
+                            data.time
+                                .push_back(device_time.unwrap_or(packet.relative_time));
+                            last_sample_arrival = Some(packet.relative_time);
+                            data.absolute_time.push_back(packet.absolute_time);
+                            data.total_samples += 1;
+                            rate_counter += 1;
+                            trim_buffer(&mut data, buffer_size);
+                            check_alarms(&mut data, &alarm_options, &mut last_alarms, &print_lock);
+                            if trigger.enable && update_trigger(&mut data, &trigger) {
+                                print_to_console(
+                                    &print_lock,
+                                    Print::Ok(format!(
+                                        "trigger captured {} samples, re-arm to capture again",
+                                        data.time.len()
+                                    )),
+                                );
+                            }
+                        }
+                        // a dropped line still differs from the dataset, unless it reset it
+                        parsed = Some(split_data.len() == data.dataset.len());
+                    }
+                    // shown in the GUI, to see how close the dataset is to a reset
+                    data.mismatched_lines = mismatch_counter;
+                    data.dropped_lines = dropped_lines;
+                    if let Some(parsed) =
+                        parsed.filter(|_| packet.direction == SerialDirection::Receive)
+                    {
+                        data.parse_quality = Some(parse_quality.push(parsed));
+                    }
+
+                    // in regex mode the columns are named after the capture groups
+                    if let Some(names) = regex_names {
+                        if names.len() == data.dataset.len() && data.header.as_ref() != Some(&names)
+                        {
+                            data.names = names.clone();
+                            data.header = Some(names);
+                        }
+                    }
+
+                    if !split_data.is_empty() {
+                        if let Some(mut header) = pending_header.take() {
+                            header.extend(parsing_options.computed.iter().map(|c| c.column_name()));
+                            if header.len() == split_data.len() {
+                                data.names = header.clone();
+                                data.header = Some(header);
+                            }
+                        }
                     }
                 }
-                // if let Ok(mut write_guard) = data_lock.write() {
-                //     *write_guard = data.clone();
-                // }
             }
         }
+        if disconnected {
+            break;
+        }
 
-        // std::thread::sleep(Duration::from_millis(10));
+        if rate_start.elapsed() >= Duration::from_secs(1) {
+            if dropped_lines > reported_dropped_lines {
+                print_to_console(
+                    &print_lock,
+                    Print::Debug(format!(
+                        "dropped {} lines with a different number of values \
+                        ({dropped_lines} dropped in total)",
+                        dropped_lines - reported_dropped_lines
+                    )),
+                );
+            }
+            reported_dropped_lines = dropped_lines;
+            let backlog = raw_data_rx.len();
+            // a backlog that keeps growing means the packets arrive faster than they are parsed
+            let was_lagging = lagging;
+            lagging = backlog > BACKLOG_WARNING && (backlog > last_backlog || was_lagging);
+            if lagging && !was_lagging {
+                print_to_console(
+                    &print_lock,
+                    Print::Error(format!(
+                        "falling behind the device, {backlog} packets are waiting to be parsed"
+                    )),
+                );
+            }
+            if let Ok(mut write_guard) = data_lock.write() {
+                let elapsed = rate_start.elapsed().as_secs_f64();
+                write_guard.samples_per_second = rate_counter as f64 / elapsed;
+                write_guard.bytes_per_second = bytes_counter as f64 / elapsed;
+                write_guard.backlog = backlog;
+                write_guard.lagging = lagging;
+                // the time without data counts from the last packet or the (re)connect
+                let connected_since = connected_lock.read().ok().and_then(|status| status.since);
+                if connected_since != watched_since {
+                    // a (re)connect starts without the stall of the previous connection
+                    write_guard.stalled = false;
+                    watched_since = connected_since;
+                }
+                if let (Some(timeout), Some(since)) = (watchdog, connected_since) {
+                    let silent = last_received.max(since).elapsed();
+                    if silent >= timeout && !write_guard.stalled {
+                        write_guard.stalled = true;
+                        print_to_console(
+                            &print_lock,
+                            Print::Error(format!(
+                                "no data received for {} s, the device may hang",
+                                silent.as_secs()
+                            )),
+                        );
+                    }
+                }
+            }
+            rate_counter = 0;
+            bytes_counter = 0;
+            rate_start = Instant::now();
+            last_backlog = backlog;
+        }
     }
 }
 
-fn main() {
-    let gui_settings = load_gui_settings();
-    let saved_serial_device_configs = load_serial_settings();
+/// The locks and channels of one serial connection and its threads.
+struct Connection {
+    device_lock: Arc<RwLock<Device>>,
+    connected_lock: Arc<RwLock<ConnectionStatus>>,
+    data_lock: Arc<RwLock<DataContainer>>,
+    /// the received bytes for the hex dump
+    byte_log_lock: Arc<RwLock<ByteLog>>,
+    send_tx: Sender<SendPayload>,
+    control_tx: Sender<PortControl>,
+    gui_event_tx: Sender<GuiEvent>,
+    record_options_tx: Sender<RecordOptions>,
+}
+
+/// The threads of a connection, kept to stop them when the connection is closed.
+struct ConnectionThreads {
+    shutdown: Arc<AtomicBool>,
+    handles: Vec<JoinHandle<()>>,
+}
 
+impl ConnectionThreads {
+    /// Stops the serial thread and waits for the main and record thread, which end once they
+    /// have handled and written everything that was received.
+    fn stop(self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        for handle in self.handles {
+            handle.join().unwrap_or_default();
+        }
+    }
+}
+
+/// Spawns the serial, record and main thread of a new connection.
+fn spawn_connection(
+    devices_lock: &Arc<RwLock<Vec<PortInfo>>>,
+    print_lock: &Arc<RwLock<Vec<Print>>>,
+) -> (Connection, ConnectionThreads) {
     let device_lock = Arc::new(RwLock::new(Device::default()));
-    let devices_lock = Arc::new(RwLock::new(vec![gui_settings.device.clone()]));
     let data_lock = Arc::new(RwLock::new(DataContainer::default()));
-    let print_lock = Arc::new(RwLock::new(vec![Print::Empty]));
-    let connected_lock = Arc::new(RwLock::new(false));
+    let connected_lock = Arc::new(RwLock::new(ConnectionStatus::default()));
+    let byte_log_lock = Arc::new(RwLock::new(ByteLog::default()));
+    let shutdown = Arc::new(AtomicBool::new(false));
 
-    let (send_tx, send_rx): (Sender<String>, Receiver<String>) = mpsc::channel();
-    let (raw_data_tx, raw_data_rx): (Sender<Packet>, Receiver<Packet>) = mpsc::channel();
-    let (gui_event_tx, gui_event_rx) = mpsc::channel::<GuiEvent>();
-    let (record_options_tx, record_options_rx) = mpsc::channel::<RecordOptions>();
-    let (record_data_tx, record_data_rx) = mpsc::channel::<RecordData>();
+    let (send_tx, send_rx) = unbounded::<SendPayload>();
+    let (control_tx, control_rx) = unbounded::<PortControl>();
+    let (raw_data_tx, raw_data_rx) = unbounded::<Packet>();
+    let (gui_event_tx, gui_event_rx) = unbounded::<GuiEvent>();
+    let (record_options_tx, record_options_rx) = unbounded::<RecordOptions>();
+    let (record_data_tx, record_data_rx) = unbounded::<RecordData>();
 
     let serial_device_lock = device_lock.clone();
     let serial_devices_lock = devices_lock.clone();
     let serial_print_lock = print_lock.clone();
     let serial_connected_lock = connected_lock.clone();
+    let serial_byte_log_lock = byte_log_lock.clone();
+    let serial_shutdown = shutdown.clone();
 
     println!("starting connection thread..");
-    let _serial_thread_handler = thread::spawn(|| {
+    let serial_thread_handler = thread::spawn(|| {
         serial_thread(
             send_rx,
+            control_rx,
             raw_data_tx,
             serial_device_lock,
             serial_devices_lock,
             serial_print_lock,
             serial_connected_lock,
+            serial_byte_log_lock,
+            serial_shutdown,
         );
     });
 
     let record_data_lock = data_lock.clone();
     let record_print_lock = print_lock.clone();
+    let raw_recording = Arc::new(AtomicBool::new(false));
+    let record_raw_recording = raw_recording.clone();
 
-    let _record_thread_handler = thread::spawn(|| {
+    let record_thread_handler = thread::spawn(|| {
         record_thread(
             record_data_lock,
             record_print_lock,
             record_options_rx,
             record_data_rx,
+            record_raw_recording,
         )
     });
 
     let main_data_lock = data_lock.clone();
     let main_print_lock = print_lock.clone();
+    let main_device_lock = device_lock.clone();
+    let main_connected_lock = connected_lock.clone();
 
     println!("starting main thread..");
-    let _main_thread_handler = thread::spawn(|| {
+    let main_thread_handler = thread::spawn(|| {
         main_thread(
             main_data_lock,
             main_print_lock,
             raw_data_rx,
             gui_event_rx,
             record_data_tx,
+            main_device_lock,
+            raw_recording,
+            main_connected_lock,
         );
     });
 
+    let connection = Connection {
+        device_lock,
+        connected_lock,
+        data_lock,
+        byte_log_lock,
+        send_tx,
+        control_tx,
+        gui_event_tx,
+        record_options_tx,
+    };
+    // joined in this order, each thread ends after the one before
+    let threads = ConnectionThreads {
+        shutdown,
+        handles: vec![
+            serial_thread_handler,
+            main_thread_handler,
+            record_thread_handler,
+        ],
+    };
+    (connection, threads)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        let result = HeadlessOptions::from_args(&args).and_then(headless::run);
+        if let Err(e) = result {
+            eprintln!("error: {e}\n{}", headless::USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let gui_settings = load_gui_settings();
+    let saved_serial_device_configs = load_serial_settings();
+
+    let devices_lock = Arc::new(RwLock::new(vec![PortInfo::new(
+        gui_settings.device.clone(),
+    )]));
+    let devices_thread_lock = devices_lock.clone();
+    thread::spawn(|| devices_thread(devices_thread_lock));
+    let print_lock = Arc::new(RwLock::new(vec![Print::Empty]));
+
+    let (connection, threads) = spawn_connection(&devices_lock, &print_lock);
+
     let options = eframe::NativeOptions {
         follow_system_theme: true,
         viewport: ViewportBuilder::default()
@@ -248,10 +1067,7 @@ fn main() {
         ..Default::default()
     };
 
-    let gui_data_lock = data_lock;
-    let gui_device_lock = device_lock;
     let gui_devices_lock = devices_lock;
-    let gui_connected_lock = connected_lock;
     let gui_print_lock = print_lock;
 
     if let Err(e) = eframe::run_native(
@@ -263,19 +1079,29 @@ fn main() {
             egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
 
             _cc.egui_ctx.set_fonts(fonts);
-            _cc.egui_ctx.set_visuals(Visuals::light());
+            _cc.egui_ctx
+                .set_zoom_factor(gui_settings.zoom_factor.clamp(0.5, 3.0));
+
+            // Ctrl-C in the terminal closes the window, so that the recordings are flushed like
+            // on a normal close. A second one exits right away.
+            let ctx = _cc.egui_ctx.clone();
+            let interrupted = AtomicBool::new(false);
+            if let Err(e) = ctrlc::set_handler(move || {
+                if interrupted.swap(true, Ordering::Relaxed) {
+                    std::process::exit(130);
+                }
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }) {
+                println!("failed to install the Ctrl-C handler: {e}");
+            }
 
             Box::new(MyApp::new(
                 gui_print_lock,
-                gui_data_lock,
-                gui_device_lock,
                 gui_devices_lock,
                 saved_serial_device_configs,
-                gui_connected_lock,
                 gui_settings,
-                send_tx,
-                gui_event_tx,
-                record_options_tx,
+                connection,
+                threads,
             ))
         }),
     ) {
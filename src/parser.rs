@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::computed::ComputedColumn;
+
+/// The character(s) used to separate the values of one line.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum Delimiter {
+    /// split on both ':' and ',' (legacy behaviour)
+    #[default]
+    CommaColon,
+    Comma,
+    Tab,
+    Semicolon,
+    Whitespace,
+    Custom(char),
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Delimiter::CommaColon => write!(f, "',' and ':'"),
+            Delimiter::Comma => write!(f, "Comma"),
+            Delimiter::Tab => write!(f, "Tab"),
+            Delimiter::Semicolon => write!(f, "Semicolon"),
+            Delimiter::Whitespace => write!(f, "Whitespace"),
+            Delimiter::Custom(c) => write!(f, "Custom ({c:?})"),
+        }
+    }
+}
+
+/// The character separating the integer and the fractional part of the received values.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum DecimalSeparator {
+    #[default]
+    Point,
+    /// `23,5` as sent by devices with a European locale
+    Comma,
+}
+
+impl fmt::Display for DecimalSeparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecimalSeparator::Point => write!(f, "Point (23.5)"),
+            DecimalSeparator::Comma => write!(f, "Comma (23,5)"),
+        }
+    }
+}
+
+/// What happens to lines with fewer values than the dataset, e.g. from a device which reports
+/// its channels at different rates.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum MissingFields {
+    /// the line is dropped (and counts towards a reset of the dataset)
+    #[default]
+    Strict,
+    /// the missing trailing values repeat the last value of their column
+    CarryForward,
+    /// the missing trailing values are NaN, a gap in the plot
+    NanFill,
+}
+
+impl fmt::Display for MissingFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MissingFields::Strict => write!(f, "Drop line"),
+            MissingFields::CarryForward => write!(f, "Carry forward"),
+            MissingFields::NanFill => write!(f, "Fill with NaN"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParsingOptions {
+    pub delimiter: Delimiter,
+    /// consecutive lines with a different number of values before the dataset is reset
+    pub reset_after_mismatches: usize,
+    /// the new number of values needs to be the same for this many lines
+    pub reset_stable_lines: usize,
+    /// extract the values with the named capture groups of a regex instead of splitting
+    pub regex_mode: bool,
+    pub regex_pattern: String,
+    /// also accept integers in hexadecimal notation (`0x1F`)
+    pub parse_hex: bool,
+    pub decimal_separator: DecimalSeparator,
+    pub missing_fields: MissingFields,
+    /// use the value of `time_column` (in ms) as the sample time instead of the arrival time
+    pub device_time: bool,
+    pub time_column: usize,
+    /// columns calculated from the parsed ones, appended after them
+    pub computed: Vec<ComputedColumn>,
+    /// the compiled `regex_pattern`, only set in regex mode
+    #[serde(skip)]
+    pub regex: Option<Regex>,
+}
+
+impl Default for ParsingOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: Delimiter::default(),
+            reset_after_mismatches: 10,
+            reset_stable_lines: 5,
+            regex_mode: false,
+            regex_pattern: "".to_string(),
+            parse_hex: false,
+            decimal_separator: DecimalSeparator::default(),
+            missing_fields: MissingFields::default(),
+            device_time: false,
+            time_column: 0,
+            computed: vec![],
+            regex: None,
+        }
+    }
+}
+
+// the compiled regex follows from the pattern
+impl PartialEq for ParsingOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.delimiter == other.delimiter
+            && self.reset_after_mismatches == other.reset_after_mismatches
+            && self.reset_stable_lines == other.reset_stable_lines
+            && self.regex_mode == other.regex_mode
+            && self.regex_pattern == other.regex_pattern
+            && self.parse_hex == other.parse_hex
+            && self.decimal_separator == other.decimal_separator
+            && self.missing_fields == other.missing_fields
+            && self.device_time == other.device_time
+            && self.time_column == other.time_column
+            && self.computed == other.computed
+    }
+}
+
+impl ParsingOptions {
+    /// Compiles the pattern if the regex mode is enabled, on error the regex is unset.
+    pub fn compile_regex(&mut self) -> Result<(), regex::Error> {
+        self.regex = None;
+        if self.regex_mode {
+            self.regex = Some(Regex::new(&self.regex_pattern)?);
+        }
+        Ok(())
+    }
+
+    /// Compiles the expressions of the computed columns, the invalid ones are reported by name.
+    pub fn compile_computed(&mut self) -> Result<(), String> {
+        let mut errors = vec![];
+        for column in self.computed.iter_mut() {
+            if let Err(e) = column.compile() {
+                errors.push(format!("{}: {e}", column.name));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+}
+
+fn split_fields<'a>(payload: &'a str, delimiter: &Delimiter) -> Vec<&'a str> {
+    match delimiter {
+        Delimiter::CommaColon => payload.split([':', ',']).collect(),
+        Delimiter::Comma => payload.split(',').collect(),
+        Delimiter::Tab => payload.split('\t').collect(),
+        Delimiter::Semicolon => payload.split(';').collect(),
+        Delimiter::Whitespace => payload.split_whitespace().collect(),
+        Delimiter::Custom(c) => payload.split(*c).collect(),
+    }
+}
+
+/// Removes ANSI escape sequences, CSI sequences like `\x1b[31m` as a whole and the character
+/// following any other escape.
+pub fn strip_ansi(payload: &str) -> Cow<str> {
+    if !payload.contains('\x1b') {
+        return Cow::Borrowed(payload);
+    }
+    let mut stripped = String::with_capacity(payload.len());
+    let mut chars = payload.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            // parameter and intermediate bytes up to the final byte (0x40..=0x7E)
+            for c in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+/// Parses a single value. `nan`, `inf` and `-inf` are accepted in any case (as by
+/// `f64::from_str`), so that a column keeps its position if the device reports an invalid reading.
+pub fn parse_value(token: &str, parse_hex: bool, decimal: DecimalSeparator) -> Option<f64> {
+    let token = match decimal {
+        DecimalSeparator::Point => Cow::Borrowed(token.trim()),
+        DecimalSeparator::Comma => Cow::Owned(token.trim().replace(',', ".")),
+    };
+    let token = token.as_ref();
+    if parse_hex {
+        let (sign, digits) = match token.strip_prefix('-') {
+            Some(rest) => (-1.0, rest),
+            None => (1.0, token),
+        };
+        if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            return u64::from_str_radix(hex, 16)
+                .ok()
+                .map(|value| sign * value as f64);
+        }
+    }
+    token.parse::<f64>().ok()
+}
+
+pub fn split(
+    payload: &str,
+    delimiter: &Delimiter,
+    parse_hex: bool,
+    decimal: DecimalSeparator,
+) -> Vec<f64> {
+    split_fields(payload, delimiter)
+        .iter()
+        .flat_map(|x| parse_value(x, parse_hex, decimal))
+        .collect()
+}
+
+/// Splits a line into trimmed, non-empty field names, used to read a header line.
+pub fn split_header(payload: &str, delimiter: &Delimiter) -> Vec<String> {
+    split_fields(payload, delimiter)
+        .iter()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Extracts the numeric values of the named capture groups, together with the group names.
+/// Returns `None` if the line does not match. A group that did not match or is not a number
+/// yields NaN, so that the values keep their columns.
+pub fn extract_named(
+    payload: &str,
+    regex: &Regex,
+    parse_hex: bool,
+    decimal: DecimalSeparator,
+) -> Option<(Vec<String>, Vec<f64>)> {
+    let captures = regex.captures(payload)?;
+    let mut names = vec![];
+    let mut values = vec![];
+    for name in regex.capture_names().flatten() {
+        let value = captures
+            .name(name)
+            .and_then(|m| parse_value(m.as_str(), parse_hex, decimal))
+            .unwrap_or(f64::NAN);
+        names.push(name.to_string());
+        values.push(value);
+    }
+    Some((names, values))
+}
@@ -1,21 +1,37 @@
+use crossbeam_channel::{Receiver, RecvTimeoutError};
 use csv::{Writer, WriterBuilder};
 use serde::{Deserialize, Serialize};
 
-use crate::data::DataContainer;
+use crate::data::{DataContainer, Packet};
 use crate::gui::{print_to_console, Print};
+use std::error::Error;
 use std::fs::{self, File};
+use std::io::Write;
 use std::path::PathBuf;
-use std::sync::mpsc::Receiver;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RecordOptions {
     pub enable: bool,
     pub record_path: PathBuf,
     pub windows_style_line_endings: bool,
     pub write_header_line: bool,
     pub insert_timestamp: bool,
+    /// write the parsed values to the CSV file
+    pub record_values: bool,
+    /// write the received payloads verbatim to a `.bin` file, with their timestamps in a sidecar
+    pub record_raw: bool,
+    /// start a new file once the current one is this large (in MB), 0 disables it
+    pub rotate_size_mb: u64,
+    /// start a new file after this many minutes, 0 disables it
+    pub rotate_minutes: u64,
+    /// record one row per this many samples, the live plot keeps all of them
+    pub decimation: usize,
+    /// record the mean of the samples of each row instead of the first one
+    pub average: bool,
 }
 
 impl Default for RecordOptions {
@@ -26,27 +42,161 @@ impl Default for RecordOptions {
             windows_style_line_endings: false,
             write_header_line: true,
             insert_timestamp: true,
+            record_values: true,
+            record_raw: false,
+            rotate_size_mb: 0,
+            rotate_minutes: 0,
+            decimation: 1,
+            average: false,
         }
     }
 }
 
-pub struct RecordData {
-    pub time: u128,
-    pub datas: Vec<f64>,
+impl RecordOptions {
+    /// The CSV file of the given part, parts after the first get a numbered suffix
+    /// (`record.csv`, `record_001.csv`, ...).
+    pub fn part_path(&self, part: usize) -> PathBuf {
+        if part == 0 {
+            return self.record_path.clone();
+        }
+        let stem = self
+            .record_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("record");
+        let mut path = self.record_path.with_file_name(format!("{stem}_{part:03}"));
+        if let Some(extension) = self.record_path.extension() {
+            path.set_extension(extension);
+        }
+        path
+    }
+
+    /// The file the raw payloads of the given part are written to.
+    pub fn raw_path(&self, part: usize) -> PathBuf {
+        self.part_path(part).with_extension("bin")
+    }
+
+    /// The sidecar with the timestamp, direction, offset and length of each raw payload.
+    pub fn raw_index_path(&self, part: usize) -> PathBuf {
+        self.part_path(part).with_extension("timestamps.csv")
+    }
+
+    /// Whether a new file should be started, given the size and age of the current one.
+    fn needs_rotation(&self, size: u64, started: Instant) -> bool {
+        (self.rotate_size_mb > 0 && size >= self.rotate_size_mb * 1_000_000)
+            || (self.rotate_minutes > 0
+                && started.elapsed() >= Duration::from_secs(self.rotate_minutes * 60))
+    }
+}
+
+pub enum RecordData {
+    /// the parsed values of one line
+    Values { time: u128, datas: Vec<f64> },
+    /// one line as it was received (or sent)
+    Raw(Packet),
+}
+
+/// Writes the raw payloads back to back, the sidecar tells where each one starts.
+struct RawRecorder {
+    bin: File,
+    index: Writer<File>,
+    offset: usize,
+}
+
+impl RawRecorder {
+    fn create(record_options: &RecordOptions, part: usize) -> Result<Self, Box<dyn Error>> {
+        let bin = File::create(record_options.raw_path(part))?;
+        let mut index = Writer::from_path(record_options.raw_index_path(part))?;
+        index.write_record(["Timestamp", "Direction", "Offset", "Length"])?;
+        Ok(Self {
+            bin,
+            index,
+            offset: 0,
+        })
+    }
+
+    fn write(&mut self, packet: &Packet) -> Result<(), Box<dyn Error>> {
+        let bytes = &packet.raw;
+        self.bin.write_all(bytes)?;
+        self.index.write_record([
+            packet.absolute_time.to_string(),
+            packet.direction.to_string(),
+            self.offset.to_string(),
+            bytes.len().to_string(),
+        ])?;
+        self.offset += bytes.len();
+        self.bin.flush()?;
+        self.index.flush()?;
+        Ok(())
+    }
+}
+
+/// Collects the samples of one recorded row when decimating.
+#[derive(Default)]
+struct Decimator {
+    count: usize,
+    first_time: u128,
+    sums: Vec<f64>,
 }
 
+impl Decimator {
+    /// Returns the row to record once per `decimation` samples: the first sample of the
+    /// bucket, or the mean of the bucket at the time of its center when averaging.
+    fn push(
+        &mut self,
+        decimation: usize,
+        average: bool,
+        time: u128,
+        datas: Vec<f64>,
+    ) -> Option<(u128, Vec<f64>)> {
+        let decimation = decimation.max(1);
+        // a changed column count starts a new bucket
+        if self.count == 0 || self.sums.len() != datas.len() {
+            self.count = 0;
+            self.first_time = time;
+            self.sums = vec![0.0; datas.len()];
+        }
+        let first = self.count == 0;
+        self.count += 1;
+        if average {
+            for (sum, data) in self.sums.iter_mut().zip(&datas) {
+                *sum += data;
+            }
+        }
+        let full = self.count == decimation;
+        if full {
+            self.count = 0;
+        }
+        match (average, first, full) {
+            (false, true, _) => Some((time, datas)),
+            (true, _, true) => Some((
+                (self.first_time + time) / 2,
+                self.sums
+                    .iter()
+                    .map(|sum| sum / decimation as f64)
+                    .collect(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The header of a row with `columns` values. The names of the dataset are used if they match
+/// the row, which they do unless the dataset was reset in the meantime.
 fn get_headers(
     data_lock: &Arc<RwLock<DataContainer>>,
     record_options: &RecordOptions,
+    columns: usize,
 ) -> Vec<String> {
     let mut headers = vec![];
     if record_options.insert_timestamp {
         headers.push("Timestamp".to_owned());
     }
-    if let Ok(read_guard) = data_lock.read() {
-        for name in &read_guard.names {
-            headers.push(name.clone());
+    match data_lock.read() {
+        Ok(read_guard) if read_guard.names.len() == columns => {
+            headers.extend(read_guard.names.iter().cloned());
         }
+        _ => headers.extend((0..columns).map(|i| format!("Column {i}"))),
     }
     headers
 }
@@ -56,38 +206,92 @@ pub fn record_thread(
     print_lock: Arc<RwLock<Vec<Print>>>,
     record_options_rx: Receiver<RecordOptions>,
     record_data_rx: Receiver<RecordData>,
+    raw_recording: Arc<AtomicBool>,
 ) {
     let mut record_options = RecordOptions::default();
     let mut wtr: Option<Writer<File>> = None;
-    'record_loop: loop {
+    // the header is written with the first row, when the names of the columns are known
+    let mut header_pending = false;
+    let mut raw_recorder: Option<RawRecorder> = None;
+    // the current file of a rotating recording and when it was started
+    let mut part: usize = 0;
+    let mut part_started = Instant::now();
+    let mut decimator = Decimator::default();
+    loop {
         if let Ok(opt) = record_options_rx.try_recv() {
             record_options = opt;
         }
+        // the main thread only sends the raw packets while they are recorded
+        raw_recording.store(
+            record_options.enable && record_options.record_raw,
+            Ordering::Relaxed,
+        );
 
-        if record_options.enable {
-            if wtr.is_none() {
-                fs::remove_file(&record_options.record_path).unwrap_or_default();
-                wtr = match WriterBuilder::new()
-                    .terminator(if record_options.windows_style_line_endings {
-                        csv::Terminator::CRLF
-                    } else {
-                        csv::Terminator::Any(b'\n')
-                    })
-                    .from_path(&record_options.record_path)
-                {
-                    Ok(w) => Some(w),
-                    Err(e) => {
-                        print_to_console(
-                            &print_lock,
-                            Print::Error(format!("Error while create recorder: {:?}", e)),
-                        );
-                        record_options.enable = false;
-                        continue;
-                    }
-                };
-                if record_options.write_header_line {
-                    if let Some(w) = &mut wtr {
-                        let headers = get_headers(&data_lock, &record_options);
+        if !record_options.enable {
+            wtr = None;
+            raw_recorder = None;
+            part = 0;
+            part_started = Instant::now();
+            decimator = Decimator::default();
+            if let Err(RecvTimeoutError::Disconnected) =
+                record_data_rx.recv_timeout(Duration::from_millis(100))
+            {
+                break;
+            }
+            // drop whatever arrived while not recording
+            for _ in record_data_rx.try_iter() {}
+            continue;
+        }
+
+        if record_options.record_values && wtr.is_none() {
+            fs::remove_file(record_options.part_path(part)).unwrap_or_default();
+            wtr = match WriterBuilder::new()
+                .terminator(if record_options.windows_style_line_endings {
+                    csv::Terminator::CRLF
+                } else {
+                    csv::Terminator::Any(b'\n')
+                })
+                .from_path(record_options.part_path(part))
+            {
+                Ok(w) => Some(w),
+                Err(e) => {
+                    print_to_console(
+                        &print_lock,
+                        Print::Error(format!("Error while create recorder: {:?}", e)),
+                    );
+                    record_options.enable = false;
+                    continue;
+                }
+            };
+            header_pending = record_options.write_header_line;
+        }
+
+        if record_options.record_raw && raw_recorder.is_none() {
+            raw_recorder = match RawRecorder::create(&record_options, part) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    print_to_console(
+                        &print_lock,
+                        Print::Error(format!("Error while create raw recorder: {:?}", e)),
+                    );
+                    record_options.enable = false;
+                    continue;
+                }
+            };
+        }
+
+        match record_data_rx.recv_timeout(Duration::from_millis(1)) {
+            Ok(RecordData::Values { time, datas }) => {
+                let row = decimator.push(
+                    record_options.decimation,
+                    record_options.average,
+                    time,
+                    datas,
+                );
+                if let (Some(w), Some((time, datas))) = (&mut wtr, row) {
+                    if header_pending {
+                        header_pending = false;
+                        let headers = get_headers(&data_lock, &record_options, datas.len());
                         if let Err(e) = w.write_record(&headers) {
                             print_to_console(
                                 &print_lock,
@@ -95,36 +299,59 @@ pub fn record_thread(
                             );
                         };
                     }
-                }
-            }
-            let datas_vec = match record_data_rx.recv_timeout(Duration::from_millis(1)) {
-                Ok(datas) => {
                     let mut dv = vec![];
                     if record_options.insert_timestamp {
-                        dv.push(datas.time.to_string())
+                        dv.push(time.to_string())
                     }
-                    for data in datas.datas {
+                    for data in datas {
                         dv.push(data.to_string())
                     }
-                    dv
+                    if let Err(e) = w.write_record(&dv) {
+                        print_to_console(
+                            &print_lock,
+                            Print::Error(format!("Error while record data: {:?}", e)),
+                        );
+                    }
+                    w.flush().unwrap_or_default();
                 }
-                Err(_) => continue 'record_loop,
-            };
-            if let Some(w) = &mut wtr {
-                if let Err(e) = w.write_record(&datas_vec) {
-                    print_to_console(
-                        &print_lock,
-                        Print::Error(format!("Error while record data: {:?}", e)),
-                    );
+            }
+            Ok(RecordData::Raw(packet)) => {
+                if let Some(r) = &mut raw_recorder {
+                    if let Err(e) = r.write(&packet) {
+                        print_to_console(
+                            &print_lock,
+                            Print::Error(format!("Error while record raw data: {:?}", e)),
+                        );
+                    }
                 }
-                w.flush().unwrap_or_default();
-            } else {
-                record_options.enable = false;
-                continue;
             }
-        } else {
+            // a quiet device still starts a new file when its time is up
+            Err(RecvTimeoutError::Timeout) if !record_options.needs_rotation(0, part_started) => {
+                continue
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // the main thread has stopped, everything it sent is written
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let size = wtr
+            .as_ref()
+            .and_then(|w| w.get_ref().metadata().ok())
+            .map_or(0, |m| m.len())
+            .max(raw_recorder.as_ref().map_or(0, |r| r.offset as u64));
+        if record_options.needs_rotation(size, part_started) {
+            // the files of the next part are created at the start of the next iteration
             wtr = None;
-            let _recv = record_data_rx.recv_timeout(Duration::from_millis(100));
+            raw_recorder = None;
+            part += 1;
+            part_started = Instant::now();
+            print_to_console(
+                &print_lock,
+                Print::Ok(format!(
+                    "continuing the recording in {:?}",
+                    record_options.part_path(part)
+                )),
+            );
         }
     }
 }
@@ -1,16 +1,24 @@
-use std::io::{BufRead, BufReader};
-use std::sync::mpsc::{Receiver, Sender};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
+use crossbeam_channel::{Receiver, Sender};
 use preferences::Preferences;
 use serde::{Deserialize, Serialize};
-use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+use serialport::{
+    DataBits, FlowControl, Parity, SerialPort, SerialPortType, StopBits, UsbPortInfo,
+};
 
 use crate::data::{get_epoch_ms, SerialDirection};
+use crate::frame::{FrameDecoder, FrameLayout};
 use crate::{print_to_console, Packet, Print, APP_INFO, PREFS_KEY_SERIAL};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SerialDevices {
     pub devices: Vec<Device>,
 }
@@ -45,7 +53,230 @@ pub fn clear_serial_settings() {
     }
 }
 
+/// An available serial port, with the USB descriptors if it is a USB device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortInfo {
+    pub name: String,
+    pub usb: Option<UsbPortInfo>,
+}
+
+impl PortInfo {
+    pub fn new(name: String) -> Self {
+        PortInfo { name, usb: None }
+    }
+
+    /// e.g. `COM3 — FTDI FT232R (0403:6001, SN A12345)`, or just the name for non-USB ports
+    pub fn label(&self) -> String {
+        match &self.usb {
+            Some(usb) => {
+                let description = [&usb.manufacturer, &usb.product]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let mut ids = format!("{:04x}:{:04x}", usb.vid, usb.pid);
+                if let Some(serial_number) = &usb.serial_number {
+                    ids += &format!(", SN {serial_number}");
+                }
+                if description.is_empty() {
+                    format!("{} ({ids})", self.name)
+                } else {
+                    format!("{} — {description} ({ids})", self.name)
+                }
+            }
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Controls the lines of an open serial port.
+#[derive(Debug, Clone)]
+pub enum PortControl {
+    SetDtr(bool),
+    SetRts(bool),
+    /// Holds the line in the break state for the given duration.
+    Break(Duration),
+}
+
+/// A command to be written to the serial port.
+#[derive(Debug, Clone)]
+pub enum SendPayload {
+    Text(String),
+    Bytes(Vec<u8>),
+    /// text lines sent one after the other, with a delay to let the device process each line,
+    /// the whole sequence is sent `repeat` times
+    Sequence {
+        lines: Vec<String>,
+        delay: Duration,
+        repeat: usize,
+    },
+    /// drops the lines of a running sequence which are not sent yet
+    StopSequence,
+    /// the contents of a file, sent in chunks of `chunk_size` bytes with a delay in between
+    File {
+        name: String,
+        bytes: Vec<u8>,
+        chunk_size: usize,
+        delay: Duration,
+    },
+    /// stops sending a file
+    CancelFile,
+}
+
+/// A file being sent in chunks, the port keeps being read in between.
+struct FileTransfer {
+    name: String,
+    bytes: Vec<u8>,
+    sent: usize,
+    chunk_size: usize,
+    delay: Duration,
+    next_chunk: Instant,
+}
+
+/// Parses space or comma separated hex tokens (e.g. "0x02 10,FF") into bytes.
+pub fn parse_hex(input: &str) -> Result<Vec<u8>, String> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let digits = token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .unwrap_or(token);
+            u8::from_str_radix(digits, 16).map_err(|_| format!("invalid hex byte: {token:?}"))
+        })
+        .collect()
+}
+
+/// The terminator appended to every command written to the port.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum LineEnding {
+    None,
+    Lf,
+    Cr,
+    #[default]
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::None => "",
+            LineEnding::Lf => "\n",
+            LineEnding::Cr => "\r",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LineEnding::None => write!(f, "None"),
+            LineEnding::Lf => write!(f, "LF (\\n)"),
+            LineEnding::Cr => write!(f, "CR (\\r)"),
+            LineEnding::CrLf => write!(f, "CRLF (\\r\\n)"),
+        }
+    }
+}
+
+/// How the received byte stream is split into packets (lines).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum Framing {
+    /// `\n`, a preceding `\r` is removed
+    #[default]
+    Newline,
+    CarriageReturn,
+    /// any terminating byte, e.g. `\0`
+    Delimiter(u8),
+    /// packets of a fixed number of bytes
+    FixedLength(usize),
+}
+
+impl Framing {
+    /// The byte which ends a packet, `None` for fixed length packets.
+    fn terminator(self) -> Option<u8> {
+        match self {
+            Framing::Newline => Some(b'\n'),
+            Framing::CarriageReturn => Some(b'\r'),
+            Framing::Delimiter(byte) => Some(byte),
+            Framing::FixedLength(_) => None,
+        }
+    }
+
+    /// The packet without its terminator (and the `\r` of a `\r\n` line ending).
+    fn strip_terminator(self, packet: &[u8]) -> &[u8] {
+        let Some(terminator) = self.terminator() else {
+            return packet;
+        };
+        let packet = packet.strip_suffix(&[terminator]).unwrap_or(packet);
+        if self == Framing::Newline {
+            packet.strip_suffix(b"\r").unwrap_or(packet)
+        } else {
+            packet
+        }
+    }
+}
+
+impl fmt::Display for Framing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Framing::Newline => write!(f, "Newline (\\n)"),
+            Framing::CarriageReturn => write!(f, "CR (\\r)"),
+            Framing::Delimiter(byte) => write!(f, "Byte (0x{byte:02X})"),
+            Framing::FixedLength(len) => write!(f, "Fixed ({len} bytes)"),
+        }
+    }
+}
+
+/// longest packet collected while waiting for its terminator, the data is passed on as a
+/// packet when the terminator does not arrive (e.g. with the wrong framing)
+const MAX_PACKET_LEN: usize = 64 * 1024;
+
+/// Collects the received bytes until a packet is complete.
+#[derive(Debug, Default)]
+struct PacketSplitter {
+    buffer: Vec<u8>,
+    /// a packet was passed on without its terminator
+    overflowed: bool,
+    warned: bool,
+}
+
+impl PacketSplitter {
+    /// Appends the bytes and returns the complete packets as received, with their terminator.
+    fn push(&mut self, bytes: &[u8], framing: Framing) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(bytes);
+        if let Framing::FixedLength(len) = framing {
+            let len = len.max(1);
+            let complete: Vec<u8> = self.buffer.drain(..self.buffer.len() / len * len).collect();
+            return complete.chunks(len).map(<[u8]>::to_vec).collect();
+        }
+        let mut packets = vec![];
+        while let Some(end) = self
+            .buffer
+            .iter()
+            .position(|b| Some(*b) == framing.terminator())
+        {
+            packets.push(self.buffer.drain(..=end).collect());
+        }
+        if self.buffer.len() >= MAX_PACKET_LEN {
+            packets.push(std::mem::take(&mut self.buffer));
+            self.overflowed = true;
+        }
+        packets
+    }
+
+    /// Returns true once per connection, after the first packet without a terminator.
+    fn warn_overflow(&mut self) -> bool {
+        let warn = self.overflowed && !self.warned;
+        self.warned |= warn;
+        warn
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct Device {
     pub name: String,
     pub baud_rate: u32,
@@ -53,7 +284,15 @@ pub struct Device {
     pub flow_control: FlowControl,
     pub parity: Parity,
     pub stop_bits: StopBits,
+    /// read timeout of the port
     pub timeout: Duration,
+    /// capacity of the buffered reader in bytes
+    pub read_buffer_size: usize,
+    pub line_ending: LineEnding,
+    /// splitting of the received text into lines
+    pub framing: Framing,
+    pub auto_reconnect: bool,
+    pub frame_layout: FrameLayout,
 }
 
 impl Default for Device {
@@ -66,43 +305,225 @@ impl Default for Device {
             parity: Parity::None,
             stop_bits: StopBits::One,
             timeout: Duration::from_millis(10),
+            read_buffer_size: 8192,
+            line_ending: LineEnding::default(),
+            framing: Framing::default(),
+            auto_reconnect: false,
+            frame_layout: FrameLayout::default(),
         }
     }
 }
 
+/// The state of a connection, shared with the GUI.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionStatus {
+    pub connected: bool,
+    /// when the port was last opened
+    pub since: Option<Instant>,
+    /// automatic reconnects since the user connected
+    pub reconnects: usize,
+    /// bytes sent and total size of a file being sent
+    pub transfer: Option<(usize, usize)>,
+}
+
+/// number of the latest received bytes kept for the hex dump
+const BYTE_LOG_CAPACITY: usize = 64 * 1024;
+/// bytes in one row of the hex dump
+pub const HEX_DUMP_ROW_BYTES: usize = 16;
+
+/// The latest received bytes as they arrived, before they are split into packets.
+#[derive(Debug, Clone, Default)]
+pub struct ByteLog {
+    pub bytes: VecDeque<u8>,
+    /// number of bytes received before the first one in `bytes`
+    pub offset: usize,
+}
+
+impl ByteLog {
+    fn push(&mut self, bytes: &[u8]) {
+        self.bytes.extend(bytes);
+        if self.bytes.len() > BYTE_LOG_CAPACITY {
+            // whole rows are dropped, so that the rows of the dump keep their offsets
+            let excess = (self.bytes.len() - BYTE_LOG_CAPACITY).div_ceil(HEX_DUMP_ROW_BYTES)
+                * HEX_DUMP_ROW_BYTES;
+            self.bytes.drain(..excess);
+            self.offset += excess;
+        }
+    }
+}
+
+/// Devices with this prefix are opened as TCP socket (e.g. `tcp://192.168.4.1:23`).
+pub const TCP_PREFIX: &str = "tcp://";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceType {
+    Serial,
+    Tcp,
+}
+
+/// An input source the connection thread reads packets from and writes commands to.
+pub trait Source: Read + Write + Send {
+    /// The underlying serial port, if this source is one.
+    fn serial_port(&mut self) -> Option<&mut dyn SerialPort> {
+        None
+    }
+}
+
+impl Source for Box<dyn SerialPort> {
+    fn serial_port(&mut self) -> Option<&mut dyn SerialPort> {
+        Some(&mut **self)
+    }
+}
+
+impl Source for TcpStream {}
+
+fn open_serial(device: &Device) -> Result<Box<dyn Source>, String> {
+    serialport::new(&device.name, device.baud_rate)
+        .data_bits(device.data_bits)
+        .stop_bits(device.stop_bits)
+        .parity(device.parity)
+        .flow_control(device.flow_control)
+        .timeout(device.timeout)
+        .open()
+        .map(|p| Box::new(p) as Box<dyn Source>)
+        .map_err(|e| {
+            // the error kind does not tell a busy port apart, the OS error of the failed call
+            // does
+            let os_error = io::Error::last_os_error().raw_os_error();
+            open_error_message(&device.name, device.baud_rate, &e, os_error)
+        })
+}
+
+/// Whether the OS error of opening a port means that another program has it open: `EBUSY`
+/// on Unix, `ERROR_ACCESS_DENIED` on Windows.
+fn is_port_busy(os_error: Option<i32>) -> bool {
+    let busy = if cfg!(windows) { 5 } else { 16 };
+    os_error == Some(busy)
+}
+
+/// The error of opening a serial port with its reason and a hint how to fix it.
+fn open_error_message(
+    name: &str,
+    baud_rate: u32,
+    error: &serialport::Error,
+    os_error: Option<i32>,
+) -> String {
+    if is_port_busy(os_error) {
+        return format!("{name}: {error} (device busy, close other programs using the port)");
+    }
+    let hint = match error.kind() {
+        serialport::ErrorKind::InvalidInput => {
+            return format!(
+                "{name}: {error} (the driver may not support {baud_rate} baud, \
+                try a standard rate)"
+            );
+        }
+        serialport::ErrorKind::NoDevice | serialport::ErrorKind::Io(io::ErrorKind::NotFound) => {
+            "no such device, check the cable and the device name"
+        }
+        serialport::ErrorKind::Io(io::ErrorKind::PermissionDenied) => {
+            if cfg!(target_os = "linux") {
+                "permission denied, add your user to the dialout group \
+                (sudo usermod -aG dialout $USER) and log in again"
+            } else {
+                "permission denied, the port may be used by another program"
+            }
+        }
+        _ => return format!("{name}: {error}"),
+    };
+    format!("{name}: {error} ({hint})")
+}
+
+fn open_tcp(address: &str, timeout: Duration) -> Result<Box<dyn Source>, String> {
+    let socket_address = address
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| format!("could not resolve {address}"))?;
+    let stream = TcpStream::connect_timeout(&socket_address, Duration::from_secs(5))
+        .map_err(|e| e.to_string())?;
+    // a read timeout of zero is not allowed for sockets
+    stream
+        .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))
+        .map_err(|e| e.to_string())?;
+    Ok(Box::new(stream))
+}
+
 fn serial_write(
-    port: &mut BufReader<Box<dyn SerialPort>>,
+    port: &mut BufReader<Box<dyn Source>>,
     cmd: &[u8],
 ) -> Result<usize, std::io::Error> {
     let write_port = port.get_mut();
     write_port.write(cmd)
 }
 
+// reads raw bytes, so that a line which is not valid UTF-8 is not dropped
 fn serial_read(
-    port: &mut BufReader<Box<dyn SerialPort>>,
-    serial_buf: &mut String,
+    port: &mut BufReader<Box<dyn Source>>,
+    serial_buf: &mut [u8],
 ) -> Result<usize, std::io::Error> {
-    port.read_line(serial_buf)
+    port.read(serial_buf)
+}
+
+/// received bytes before the share of garbled text is judged
+const GARBLED_MIN_CHARS: usize = 256;
+/// share of garbled characters above which a baud rate mismatch is likely
+const GARBLED_RATIO: f64 = 0.3;
+
+/// Counts the received characters which are not printable text. If the baud rate does not
+/// match the device most of them are invalid UTF-8 or control characters.
+#[derive(Default)]
+struct GarbledText {
+    chars: usize,
+    garbled: usize,
+    warned: bool,
+}
+
+impl GarbledText {
+    fn count(&mut self, text: &str) {
+        if self.warned {
+            return;
+        }
+        for c in text.chars() {
+            self.chars += 1;
+            // '\0' separates lines and '\x1b' starts ANSI escape sequences
+            if c == char::REPLACEMENT_CHARACTER
+                || (c.is_control() && !matches!(c, '\t' | '\r' | '\n' | '\0' | '\x1b'))
+            {
+                self.garbled += 1;
+            }
+        }
+    }
+
+    /// Returns true once per connection, when enough text was received and too much of it
+    /// was garbled.
+    fn warn(&mut self) -> bool {
+        if self.warned || self.chars < GARBLED_MIN_CHARS {
+            return false;
+        }
+        self.warned = self.garbled as f64 > GARBLED_RATIO * self.chars as f64;
+        self.warned
+    }
 }
 
+/// the first and the longest wait between failed attempts to reopen a port that reappeared
+const RECONNECT_DELAY_MIN: Duration = Duration::from_millis(250);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(5);
+
 pub fn serial_thread(
-    send_rx: Receiver<String>,
+    send_rx: Receiver<SendPayload>,
+    control_rx: Receiver<PortControl>,
     raw_data_tx: Sender<Packet>,
     device_lock: Arc<RwLock<Device>>,
-    devices_lock: Arc<RwLock<Vec<String>>>,
+    devices_lock: Arc<RwLock<Vec<PortInfo>>>,
     print_lock: Arc<RwLock<Vec<Print>>>,
-    connected_lock: Arc<RwLock<bool>>,
+    connected_lock: Arc<RwLock<ConnectionStatus>>,
+    byte_log_lock: Arc<RwLock<ByteLog>>,
+    shutdown: Arc<AtomicBool>,
 ) {
-    let devices_update_lock = devices_lock.clone();
-    let _devices_update_handler = std::thread::spawn(move || loop {
-        let devices = available_devices();
-        if let Ok(mut write_guard) = devices_update_lock.write() {
-            *write_guard = devices;
-        }
-        std::thread::sleep(Duration::from_millis(200));
-    });
-
-    loop {
+    let mut reconnecting = false;
+    let mut retry_delay = RECONNECT_DELAY_MIN;
+    while !shutdown.load(Ordering::Relaxed) {
         let _not_awake = keepawake::Builder::default()
             .display(false)
             .reason("Serial Connection")
@@ -110,34 +531,96 @@ pub fn serial_thread(
             //.app_reverse_domain("io.github.myprog")
             .create();
 
-        if let Ok(mut connected) = connected_lock.write() {
-            *connected = false;
+        if let Ok(mut status) = connected_lock.write() {
+            status.connected = false;
+            status.since = None;
+            status.transfer = None;
         }
 
-        let device = get_device(&devices_lock, &device_lock);
+        let Some(device) = get_device(&devices_lock, &device_lock, &shutdown) else {
+            break;
+        };
 
-        let mut port = match serialport::new(&device.name, device.baud_rate)
-            .data_bits(device.data_bits)
-            .stop_bits(device.stop_bits)
-            .parity(device.parity)
-            .flow_control(device.flow_control)
-            .timeout(device.timeout)
-            .open()
-        {
-            Ok(p) => {
-                if let Ok(mut connected) = connected_lock.write() {
-                    *connected = true;
+        let source = match device.name.strip_prefix(TCP_PREFIX) {
+            Some(address) => open_tcp(address, device.timeout),
+            None => open_serial(&device),
+        };
+
+        let mut port = match source {
+            Ok(mut p) => {
+                retry_delay = RECONNECT_DELAY_MIN;
+                if let Ok(mut status) = connected_lock.write() {
+                    status.connected = true;
+                    status.since = Some(Instant::now());
+                    // a manual connect starts counting again
+                    status.reconnects = if reconnecting {
+                        status.reconnects + 1
+                    } else {
+                        0
+                    };
                 }
+                // some drivers silently round or clamp a rate they do not support
+                let applied_baud_rate = p.serial_port().and_then(|port| port.baud_rate().ok());
+                if let Some(applied) = applied_baud_rate.filter(|b| *b != device.baud_rate) {
+                    print_to_console(
+                        &print_lock,
+                        Print::Error(format!(
+                            "{}: requested {} baud, but the driver applied {} baud",
+                            device.name, device.baud_rate, applied
+                        )),
+                    );
+                }
+                let message = if reconnecting {
+                    format!("Reconnected to serial port: {}", device.name)
+                } else if device.name.starts_with(TCP_PREFIX) {
+                    format!("Connected to {}", device.name)
+                } else {
+                    format!(
+                        "Connected to serial port: {} @ baud = {}, {}{}{}",
+                        device.name,
+                        device.baud_rate,
+                        device.data_bits,
+                        match device.parity {
+                            Parity::None => "N",
+                            Parity::Odd => "O",
+                            Parity::Even => "E",
+                        },
+                        device.stop_bits
+                    )
+                };
+                print_to_console(&print_lock, Print::Ok(message));
+                BufReader::with_capacity(device.read_buffer_size.max(1), p)
+            }
+            Err(err) if reconnecting && device.auto_reconnect => {
+                // a port often fails to open right after it reappears, keep trying until the
+                // user disconnects
                 print_to_console(
                     &print_lock,
-                    Print::Ok(format!(
-                        "Connected to serial port: {} @ baud = {}",
-                        device.name, device.baud_rate
+                    Print::Error(format!(
+                        "Error reconnecting: {}, retrying in {} ms",
+                        err,
+                        retry_delay.as_millis()
                     )),
                 );
-                BufReader::new(p)
+                let still_selected = || {
+                    device_lock
+                        .read()
+                        .map(|d| d.name == device.name)
+                        .unwrap_or(false)
+                };
+                let retry_at = Instant::now() + retry_delay;
+                while Instant::now() < retry_at && !shutdown.load(Ordering::Relaxed) {
+                    if !still_selected() {
+                        break;
+                    }
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                retry_delay = (retry_delay * 2).min(RECONNECT_DELAY_MAX);
+                reconnecting = still_selected();
+                continue;
             }
             Err(err) => {
+                reconnecting = false;
                 if let Ok(mut write_guard) = device_lock.write() {
                     write_guard.name.clear();
                 }
@@ -158,51 +641,167 @@ pub fn serial_thread(
             //.app_reverse_domain("io.github.myprog")
             .create();
 
+        let mut frame_decoder = FrameDecoder::default();
+        let mut splitter = PacketSplitter::default();
+        let mut garbled = GarbledText::default();
+        let mut read_buf = vec![0u8; port.capacity()];
+        // the scheduled lines of a command sequence, with the time they are due
+        let mut sequence = VecDeque::new();
+        let mut transfer = None;
+
         'connected_loop: loop {
+            if shutdown.load(Ordering::Relaxed) {
+                break 'connected_loop;
+            }
             if let Some(message) = disconnected(&device, &devices_lock, &device_lock) {
                 print_to_console(&print_lock, message);
                 break 'connected_loop;
             }
 
-            perform_writes(&mut port, &send_rx, &raw_data_tx, t_zero);
-            perform_reads(&mut port, &raw_data_tx, t_zero);
+            // the line ending, the framing and the frame layout may be changed while connected
+            let (line_ending, framing, frame_layout) = device_lock
+                .read()
+                .map(|d| (d.line_ending, d.framing, d.frame_layout.clone()))
+                .unwrap_or_else(|_| {
+                    (
+                        device.line_ending,
+                        device.framing,
+                        device.frame_layout.clone(),
+                    )
+                });
+            perform_control(&mut port, &control_rx, &print_lock, &shutdown);
+            perform_writes(
+                &mut port,
+                &send_rx,
+                &mut sequence,
+                &mut transfer,
+                &raw_data_tx,
+                line_ending,
+                t_zero,
+            );
+            let had_transfer = transfer.is_some();
+            if let Some(message) = perform_file_transfer(&mut port, &mut transfer) {
+                print_to_console(&print_lock, message);
+            }
+            if had_transfer || transfer.is_some() {
+                if let Ok(mut status) = connected_lock.write() {
+                    status.transfer = transfer.as_ref().map(|t| (t.sent, t.bytes.len()));
+                }
+            }
+            let open = if frame_layout.enable {
+                perform_frame_reads(
+                    &mut port,
+                    &mut read_buf,
+                    &mut frame_decoder,
+                    &frame_layout,
+                    &raw_data_tx,
+                    &byte_log_lock,
+                    t_zero,
+                )
+            } else {
+                perform_reads(
+                    &mut port,
+                    &mut read_buf,
+                    &mut splitter,
+                    framing,
+                    &mut garbled,
+                    &raw_data_tx,
+                    &byte_log_lock,
+                    t_zero,
+                )
+            };
+            if splitter.warn_overflow() {
+                print_to_console(
+                    &print_lock,
+                    Print::Error(format!(
+                        "No {framing} within {MAX_PACKET_LEN} bytes from {}, check the framing",
+                        device.name
+                    )),
+                );
+            }
+            if garbled.warn() && !device.name.starts_with(TCP_PREFIX) {
+                print_to_console(
+                    &print_lock,
+                    Print::Error(format!(
+                        "The data received from {} looks garbled, check that the baud rate \
+                        ({}) matches the device",
+                        device.name, device.baud_rate
+                    )),
+                );
+            }
+            if !open {
+                print_to_console(
+                    &print_lock,
+                    Print::Error(format!("Connection closed by {}", device.name)),
+                );
+                if let Ok(mut write_guard) = device_lock.write() {
+                    write_guard.name.clear();
+                }
+                break 'connected_loop;
+            }
 
             //std::thread::sleep(Duration::from_millis(10));
         }
         std::mem::drop(port);
+
+        // if the device is still selected after the disconnect, we wait for it to reappear
+        reconnecting = device_lock
+            .read()
+            .map(|d| d.name == device.name)
+            .unwrap_or(false);
+    }
+}
+
+/// Keeps the list of available ports up to date, one thread serves all connections.
+pub fn devices_thread(devices_lock: Arc<RwLock<Vec<PortInfo>>>) {
+    loop {
+        let devices = available_devices();
+        if let Ok(mut write_guard) = devices_lock.write() {
+            *write_guard = devices;
+        }
+        std::thread::sleep(Duration::from_millis(200));
     }
 }
 
-fn available_devices() -> Vec<String> {
+fn available_devices() -> Vec<PortInfo> {
     serialport::available_ports()
         .unwrap()
-        .iter()
-        .map(|p| p.port_name.clone())
+        .into_iter()
+        .map(|p| PortInfo {
+            name: p.port_name,
+            usb: match p.port_type {
+                SerialPortType::UsbPort(info) => Some(info),
+                _ => None,
+            },
+        })
         .collect()
 }
 
+pub fn contains_device(devices: &[PortInfo], name: &str) -> bool {
+    devices.iter().any(|d| d.name == name)
+}
+
+/// Waits until the selected device is available, `None` if the connection is shut down.
+/// The available ports are listed by `devices_thread`.
 fn get_device(
-    devices_lock: &Arc<RwLock<Vec<String>>>,
+    devices_lock: &Arc<RwLock<Vec<PortInfo>>>,
     device_lock: &Arc<RwLock<Device>>,
-) -> Device {
-    loop {
-        let devices = available_devices();
-        if let Ok(mut write_guard) = devices_lock.write() {
-            *write_guard = devices.clone();
-        }
-
-        if let Ok(device) = device_lock.read() {
-            if devices.contains(&device.name) {
-                return device.clone();
+    shutdown: &AtomicBool,
+) -> Option<Device> {
+    while !shutdown.load(Ordering::Relaxed) {
+        if let (Ok(device), Ok(devices)) = (device_lock.read(), devices_lock.read()) {
+            if device.name.starts_with(TCP_PREFIX) || contains_device(&devices, &device.name) {
+                return Some(device.clone());
             }
         }
         std::thread::sleep(Duration::from_millis(100));
     }
+    None
 }
 
 fn disconnected(
     device: &Device,
-    devices_lock: &Arc<RwLock<Vec<String>>>,
+    devices_lock: &Arc<RwLock<Vec<PortInfo>>>,
     device_lock: &Arc<RwLock<Device>>,
 ) -> Option<Print> {
     // disconnection by button press
@@ -213,12 +812,29 @@ fn disconnected(
                 device.name
             )));
         }
+        // the port needs to be reopened to change the flow control
+        if device.flow_control != read_guard.flow_control {
+            return Some(Print::Ok(format!(
+                "Reopening serial port: {} with flow control = {}",
+                device.name, read_guard.flow_control
+            )));
+        }
     }
 
     // other types of disconnection (e.g. unplugging, power down)
+    if device.name.starts_with(TCP_PREFIX) {
+        return None;
+    }
     if let Ok(devices) = devices_lock.read() {
-        if !devices.contains(&device.name) {
+        if !contains_device(&devices, &device.name) {
             if let Ok(mut write_guard) = device_lock.write() {
+                if write_guard.auto_reconnect {
+                    // keep the name, get_device() will wait until the port is back
+                    return Some(Print::Error(format!(
+                        "Device has disconnected from serial port: {}, waiting to reconnect..",
+                        device.name
+                    )));
+                }
                 write_guard.name.clear();
             }
             return Some(Print::Error(format!(
@@ -231,14 +847,140 @@ fn disconnected(
     None
 }
 
+/// the break is held in slices of this length, to notice a shutdown in between
+const BREAK_SLICE: Duration = Duration::from_millis(50);
+
+fn perform_control(
+    port: &mut BufReader<Box<dyn Source>>,
+    control_rx: &Receiver<PortControl>,
+    print_lock: &Arc<RwLock<Vec<Print>>>,
+    shutdown: &AtomicBool,
+) {
+    if let Ok(control) = control_rx.try_recv() {
+        let serial_port = match port.get_mut().serial_port() {
+            Some(p) => p,
+            None => {
+                print_to_console(
+                    print_lock,
+                    Print::Error("Line control is only available for serial ports".to_string()),
+                );
+                return;
+            }
+        };
+        let result = match control {
+            PortControl::SetDtr(level) => serial_port.write_data_terminal_ready(level),
+            PortControl::SetRts(level) => serial_port.write_request_to_send(level),
+            PortControl::Break(duration) => serial_port.set_break().and_then(|_| {
+                let end = Instant::now() + duration;
+                while !shutdown.load(Ordering::Relaxed) {
+                    let left = end.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        break;
+                    }
+                    std::thread::sleep(left.min(BREAK_SLICE));
+                }
+                serial_port.clear_break()
+            }),
+        };
+        match result {
+            Ok(_) => {
+                if let PortControl::Break(duration) = control {
+                    print_to_console(
+                        print_lock,
+                        Print::Ok(format!("Sent break for {} ms", duration.as_millis())),
+                    );
+                }
+            }
+            Err(e) => {
+                print_to_console(
+                    print_lock,
+                    Print::Error(format!("Error setting {:?}: {}", control, e)),
+                );
+            }
+        }
+    }
+}
+
 fn perform_writes(
-    port: &mut BufReader<Box<dyn SerialPort>>,
-    send_rx: &Receiver<String>,
+    port: &mut BufReader<Box<dyn Source>>,
+    send_rx: &Receiver<SendPayload>,
+    sequence: &mut VecDeque<(Instant, String)>,
+    transfer: &mut Option<FileTransfer>,
     raw_data_tx: &Sender<Packet>,
+    line_ending: LineEnding,
     t_zero: Instant,
 ) {
-    if let Ok(cmd) = send_rx.try_recv() {
-        if let Err(e) = serial_write(port, cmd.as_bytes()) {
+    let payload = match send_rx.try_recv() {
+        Ok(SendPayload::Sequence {
+            lines,
+            delay,
+            repeat,
+        }) => {
+            // the lines are scheduled instead of sent, the port keeps being read in between
+            let start = Instant::now();
+            let lines = lines.iter().cycle().take(lines.len() * repeat);
+            for (i, line) in lines.enumerate() {
+                sequence.push_back((start + delay * i as u32, line.clone()));
+            }
+            None
+        }
+        Ok(SendPayload::StopSequence) => {
+            sequence.clear();
+            None
+        }
+        Ok(SendPayload::File {
+            name,
+            bytes,
+            chunk_size,
+            delay,
+        }) => {
+            *transfer = Some(FileTransfer {
+                name,
+                bytes,
+                sent: 0,
+                chunk_size: chunk_size.max(1),
+                delay,
+                next_chunk: Instant::now(),
+            });
+            None
+        }
+        Ok(SendPayload::CancelFile) => {
+            *transfer = None;
+            None
+        }
+        Ok(payload) => Some(payload),
+        Err(_) => None,
+    };
+    let payload = payload.or_else(|| {
+        let (due, _) = sequence.front()?;
+        if *due > Instant::now() {
+            return None;
+        }
+        sequence
+            .pop_front()
+            .map(|(_, line)| SendPayload::Text(line))
+    });
+    if let Some(payload) = payload {
+        let (bytes, cmd) = match payload {
+            SendPayload::Text(text) => {
+                let cmd = text + line_ending.as_str();
+                (cmd.as_bytes().to_vec(), cmd)
+            }
+            // raw bytes are sent verbatim, without a line ending
+            SendPayload::Bytes(bytes) => {
+                let cmd = bytes
+                    .iter()
+                    .map(|b| format!("{b:02X}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (bytes, cmd)
+            }
+            SendPayload::Sequence { .. }
+            | SendPayload::StopSequence
+            | SendPayload::File { .. }
+            | SendPayload::CancelFile => return,
+        };
+        if let Err(e) = serial_write(port, &bytes) {
             println!("Error sending command: {e}");
             return;
         }
@@ -248,6 +990,8 @@ fn perform_writes(
             absolute_time: get_epoch_ms(),
             direction: SerialDirection::Send,
             payload: cmd,
+            raw: bytes,
+            values: None,
         };
         raw_data_tx
             .send(packet)
@@ -255,29 +999,127 @@ fn perform_writes(
     }
 }
 
+/// Writes the next chunk of a file when it is due. Returns a message once the file is sent
+/// or the transfer failed.
+fn perform_file_transfer(
+    port: &mut BufReader<Box<dyn Source>>,
+    transfer: &mut Option<FileTransfer>,
+) -> Option<Print> {
+    let file = transfer.as_mut()?;
+    if file.next_chunk > Instant::now() {
+        return None;
+    }
+    let end = (file.sent + file.chunk_size).min(file.bytes.len());
+    match serial_write(port, &file.bytes[file.sent..end]) {
+        // with flow control the device may take only a part, the rest follows with the next chunk
+        Ok(n) => {
+            file.sent += n;
+            file.next_chunk = Instant::now() + file.delay;
+        }
+        // the device holds back the data (flow control), the chunk is written again
+        Err(ref e)
+            if e.kind() == std::io::ErrorKind::TimedOut
+                || e.kind() == std::io::ErrorKind::WouldBlock => {}
+        Err(e) => {
+            let message = format!(
+                "Sending {} failed after {} bytes: {e}",
+                file.name, file.sent
+            );
+            *transfer = None;
+            return Some(Print::Error(message));
+        }
+    }
+    if file.sent < file.bytes.len() {
+        return None;
+    }
+    let message = format!("Sent {} ({} bytes)", file.name, file.bytes.len());
+    *transfer = None;
+    Some(Print::Ok(message))
+}
+
+/// Returns false if the other end closed the connection.
 fn perform_reads(
-    port: &mut BufReader<Box<dyn SerialPort>>,
+    port: &mut BufReader<Box<dyn Source>>,
+    buf: &mut [u8],
+    splitter: &mut PacketSplitter,
+    framing: Framing,
+    garbled: &mut GarbledText,
     raw_data_tx: &Sender<Packet>,
+    byte_log_lock: &Arc<RwLock<ByteLog>>,
     t_zero: Instant,
-) {
-    let mut buf = "".to_string();
-    match serial_read(port, &mut buf) {
-        Ok(_) => {
-            let delimiter = if buf.contains("\r\n") { "\r\n" } else { "\0\0" };
-            buf.split_terminator(delimiter).for_each(|s| {
+) -> bool {
+    match serial_read(port, buf) {
+        // only a closed socket returns zero bytes, serial ports time out instead
+        Ok(0) => return false,
+        Ok(n) => {
+            if let Ok(mut byte_log) = byte_log_lock.write() {
+                byte_log.push(&buf[..n]);
+            }
+            for bytes in splitter.push(&buf[..n], framing) {
+                let payload = String::from_utf8_lossy(framing.strip_terminator(&bytes));
+                garbled.count(&payload);
                 let packet = Packet {
                     relative_time: Instant::now().duration_since(t_zero).as_millis(),
                     absolute_time: get_epoch_ms(),
                     direction: SerialDirection::Receive,
-                    payload: s.to_owned(),
+                    payload: payload.into_owned(),
+                    raw: bytes,
+                    values: None,
                 };
                 raw_data_tx.send(packet).expect("failed to send raw data");
-            });
+            }
+        }
+        // Timeout is ok, just means there is no data to read (sockets report WouldBlock)
+        Err(ref e)
+            if e.kind() == std::io::ErrorKind::TimedOut
+                || e.kind() == std::io::ErrorKind::WouldBlock => {}
+        Err(e) => {
+            println!("Error reading: {:?}", e);
+        }
+    }
+    true
+}
+
+/// Reads the available bytes and sends a packet for every complete binary frame.
+/// Returns false if the other end closed the connection.
+fn perform_frame_reads(
+    port: &mut BufReader<Box<dyn Source>>,
+    buf: &mut [u8],
+    frame_decoder: &mut FrameDecoder,
+    frame_layout: &FrameLayout,
+    raw_data_tx: &Sender<Packet>,
+    byte_log_lock: &Arc<RwLock<ByteLog>>,
+    t_zero: Instant,
+) -> bool {
+    match port.read(buf) {
+        // only a closed socket returns zero bytes, serial ports time out instead
+        Ok(0) => return false,
+        Ok(n) => {
+            if let Ok(mut byte_log) = byte_log_lock.write() {
+                byte_log.push(&buf[..n]);
+            }
+            for (frame, values) in frame_decoder.push(&buf[..n], frame_layout) {
+                let packet = Packet {
+                    relative_time: Instant::now().duration_since(t_zero).as_millis(),
+                    absolute_time: get_epoch_ms(),
+                    direction: SerialDirection::Receive,
+                    payload: frame
+                        .iter()
+                        .map(|b| format!("{b:02X}"))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    raw: frame,
+                    values: Some(values),
+                };
+                raw_data_tx.send(packet).expect("failed to send raw data");
+            }
         }
-        // Timeout is ok, just means there is no data to read
-        Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+        Err(ref e)
+            if e.kind() == std::io::ErrorKind::TimedOut
+                || e.kind() == std::io::ErrorKind::WouldBlock => {}
         Err(e) => {
             println!("Error reading: {:?}", e);
         }
     }
+    true
 }